@@ -0,0 +1,25 @@
+//! Prints magnetometer readings from a LIS3MDL attached to a Linux I2C bus.
+//!
+//! Run with `cargo run --example linux --features linux -- /dev/i2c-1`.
+
+use std::{env, thread, time::Duration};
+
+use lis3mdl::{Error, LIS3MDL};
+
+fn main() {
+    let path = env::args().nth(1).unwrap_or_else(|| "/dev/i2c-1".into());
+
+    let mut lis3mdl = LIS3MDL::open_i2c(&path).expect("no LIS3MDL found");
+    lis3mdl
+        .init_default()
+        .expect("failed to configure the LIS3MDL");
+
+    loop {
+        match lis3mdl.read() {
+            Ok((x, y, z)) => println!("x: {:6} y: {:6} z: {:6}", x, y, z),
+            Err(Error::NotReady) => {}
+            Err(e) => panic!("failed to read the LIS3MDL: {:?}", e),
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,729 @@
+//! Async version of the driver, built on [`embedded_hal_async`].
+//!
+//! [`LIS3MDLAsync`] mirrors the blocking [`LIS3MDL`](crate::LIS3MDL) and shares its
+//! register encodings, so the two always configure the chip identically.
+
+use core::time::Duration;
+
+use embedded_hal::digital::Error as _;
+use embedded_hal::i2c::Error as _;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::i2c::{self, I2c};
+use embedded_hal_async::spi::{Operation, SpiDevice};
+
+use crate::config::Shadow;
+use crate::interface::{i2c_sub_address, spi_read_command, spi_write_command};
+use crate::interrupt::{threshold_from_raw, threshold_to_raw, WakeState};
+use crate::measurement::{to_celsius, to_field, to_milligauss};
+use crate::registers::{
+    CtrlReg1, CtrlReg2, CtrlReg3, CtrlReg4, CtrlReg5, IntCfg, IntSrc, StatusReg,
+};
+use crate::self_test::{self, SelfTestReport};
+use crate::{
+    decode_measurements, registers, Axes, AxisMode, Config, Error, FullScale, I2cInterface,
+    InterruptConfig, MagneticField, OperatingMode, OutputDataRate, Sample, SlaveAddr, SpiInterface,
+    State, LIS3MDL_SA1_HIGH_ADDRESS, LIS3MDL_SA1_LOW_ADDRESS, LIS3MDL_WHO_ID,
+    SINGLE_CONVERSION_POLL_US,
+};
+
+/// Async register level access to the LIS3MDL over some bus.
+/// This is the async counterpart of [`Interface`](crate::Interface).
+#[allow(async_fn_in_trait)]
+pub trait AsyncInterface {
+    type Error;
+
+    /// Set one of the LIS3MDL's registers to a certain value
+    async fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Self::Error>;
+
+    /// Burst write `values` to consecutive registers, starting at `reg`, in a single transaction
+    async fn write_registers(&mut self, reg: u8, values: &[u8]) -> Result<(), Self::Error>;
+
+    /// Burst read consecutive registers, starting at `reg`, into `buf`
+    async fn read_registers(&mut self, reg: u8, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Read one of the LIS3MDL's registers
+    async fn read_register(&mut self, reg: u8) -> Result<u8, Self::Error> {
+        let mut resp = [0];
+        self.read_registers(reg, &mut resp).await?;
+        Ok(resp[0])
+    }
+}
+
+impl<T: AsyncInterface + ?Sized> AsyncInterface for &mut T {
+    type Error = T::Error;
+
+    async fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Self::Error> {
+        T::write_register(self, reg, value).await
+    }
+
+    async fn write_registers(&mut self, reg: u8, values: &[u8]) -> Result<(), Self::Error> {
+        T::write_registers(self, reg, values).await
+    }
+
+    async fn read_registers(&mut self, reg: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        T::read_registers(self, reg, buf).await
+    }
+
+    async fn read_register(&mut self, reg: u8) -> Result<u8, Self::Error> {
+        T::read_register(self, reg).await
+    }
+}
+
+impl<I: I2c> AsyncInterface for I2cInterface<I> {
+    type Error = I::Error;
+
+    async fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Self::Error> {
+        self.i2c.write(self.address, &[reg, value]).await
+    }
+
+    async fn write_registers(&mut self, reg: u8, values: &[u8]) -> Result<(), Self::Error> {
+        let sub_address = i2c_sub_address(reg, values.len());
+        self.i2c
+            .transaction(
+                self.address,
+                &mut [
+                    i2c::Operation::Write(&[sub_address]),
+                    i2c::Operation::Write(values),
+                ],
+            )
+            .await
+    }
+
+    async fn read_registers(&mut self, reg: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let sub_address = i2c_sub_address(reg, buf.len());
+        self.i2c.write_read(self.address, &[sub_address], buf).await
+    }
+}
+
+impl<S: SpiDevice> AsyncInterface for SpiInterface<S> {
+    type Error = S::Error;
+
+    async fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Self::Error> {
+        self.spi.write(&[reg, value]).await
+    }
+
+    async fn write_registers(&mut self, reg: u8, values: &[u8]) -> Result<(), Self::Error> {
+        let command = spi_write_command(reg, values.len());
+        self.spi
+            .transaction(&mut [Operation::Write(&[command]), Operation::Write(values)])
+            .await
+    }
+
+    async fn read_registers(&mut self, reg: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let command = spi_read_command(reg, buf.len());
+        self.spi
+            .transaction(&mut [Operation::Write(&[command]), Operation::Read(buf)])
+            .await
+    }
+}
+
+pub struct LIS3MDLAsync<DI> {
+    iface: DI,
+    shadow: Shadow,
+    wake: Option<WakeState>,
+}
+
+impl<I: I2c> LIS3MDLAsync<I2cInterface<I>> {
+    pub async fn new(mut i2c: I) -> Result<Self, Error<I::Error>> {
+        // Get the correct address for the LIS3MDL that is being used
+        let first = match read_who_am_i(&mut i2c, LIS3MDL_SA1_HIGH_ADDRESS).await {
+            Ok(id) => Some(id),
+            // Nothing answers here on boards with SA1 pulled low, so try the other address
+            Err(e) if matches!(e.kind(), i2c::ErrorKind::NoAcknowledge(_)) => None,
+            Err(e) => return Err(Error::Bus(e)),
+        };
+        let address = if first == Some(LIS3MDL_WHO_ID) {
+            LIS3MDL_SA1_HIGH_ADDRESS
+        } else {
+            let id = match read_who_am_i(&mut i2c, LIS3MDL_SA1_LOW_ADDRESS).await {
+                Ok(id) => id,
+                // If only the first address answered, what it answered with is the real problem
+                Err(e) => {
+                    return Err(match first {
+                        Some(id) if matches!(e.kind(), i2c::ErrorKind::NoAcknowledge(_)) => {
+                            Error::WrongDeviceId(id)
+                        }
+                        _ => Error::Bus(e),
+                    })
+                }
+            };
+            if id != LIS3MDL_WHO_ID {
+                return Err(Error::WrongDeviceId(id));
+            }
+            LIS3MDL_SA1_LOW_ADDRESS
+        };
+
+        let mut this = Self {
+            iface: I2cInterface::new(i2c, address),
+            shadow: Shadow::default(),
+            wake: None,
+        };
+        this.resync().await?;
+        Ok(this)
+    }
+
+    /// See [`LIS3MDL::new_with_address`](crate::LIS3MDL::new_with_address).
+    pub fn new_with_address(i2c: I, address: SlaveAddr) -> Self {
+        Self {
+            iface: I2cInterface::new(i2c, address.addr()),
+            shadow: Shadow::default(),
+            wake: None,
+        }
+    }
+
+    /// See [`LIS3MDL::new_with_address_verified`](crate::LIS3MDL::new_with_address_verified).
+    pub async fn new_with_address_verified(
+        i2c: I,
+        address: SlaveAddr,
+    ) -> Result<Self, Error<I::Error>> {
+        Self::new_with_interface(I2cInterface::new(i2c, address.addr())).await
+    }
+
+    /// Destroys the driver, giving back the I2C bus.
+    pub fn destroy(self) -> I {
+        self.iface.destroy()
+    }
+}
+
+impl<S: SpiDevice> LIS3MDLAsync<SpiInterface<S>> {
+    /// Creates a driver for a LIS3MDL wired over 4-wire SPI, returning `Error::WrongDeviceId`
+    /// if the device on the other end does not identify as a LIS3MDL.
+    pub async fn new_spi(spi: S) -> Result<Self, Error<S::Error>> {
+        Self::new_with_interface(SpiInterface::new(spi)).await
+    }
+
+    /// Destroys the driver, giving back the SPI device.
+    pub fn destroy(self) -> S {
+        self.iface.destroy()
+    }
+}
+
+impl<DI: AsyncInterface> LIS3MDLAsync<DI> {
+    /// Creates a driver on top of an arbitrary [`AsyncInterface`], returning `Error::WrongDeviceId`
+    /// if the device on the other end does not identify as a LIS3MDL.
+    pub async fn new_with_interface(mut iface: DI) -> Result<Self, Error<DI::Error>> {
+        let id = iface
+            .read_register(registers::WHO_AM_I)
+            .await
+            .map_err(Error::Bus)?;
+        if id != LIS3MDL_WHO_ID {
+            return Err(Error::WrongDeviceId(id));
+        }
+
+        let mut this = Self {
+            iface,
+            shadow: Shadow::default(),
+            wake: None,
+        };
+        this.resync().await?;
+        Ok(this)
+    }
+
+    /// Destroys the driver, giving back the interface it was built on.
+    pub fn into_interface(self) -> DI {
+        self.iface
+    }
+
+    /// See [`LIS3MDL::release`](crate::LIS3MDL::release).
+    pub fn release(self) -> (DI, State) {
+        let state = State {
+            shadow: self.shadow,
+            wake: self.wake,
+        };
+        (self.iface, state)
+    }
+
+    /// See [`LIS3MDL::attach`](crate::LIS3MDL::attach).
+    pub fn attach(iface: DI, state: State) -> Self {
+        Self {
+            iface,
+            shadow: state.shadow,
+            wake: state.wake,
+        }
+    }
+
+    /// Reads the WHO_AM_I register, which is 0x3D on a genuine LIS3MDL.
+    pub async fn who_am_i(&mut self) -> Result<u8, Error<DI::Error>> {
+        self.read_register(registers::WHO_AM_I).await
+    }
+
+    /// Initialize the lis3mdl in high performance axis modes, continous conversion mode,
+    /// and 10 Hz output data rate.
+    pub async fn init_default(&mut self) -> Result<(), Error<DI::Error>> {
+        self.set_xy_mode_and_data_rate(AxisMode::HighPerformance, OutputDataRate::Hz10)
+            .await?;
+        self.set_z_mode(AxisMode::HighPerformance).await?;
+        self.set_full_scale(FullScale::Four).await?;
+        self.set_operating_mode(OperatingMode::ContinuousConversion)
+            .await
+    }
+
+    /// See [`LIS3MDL::apply`](crate::LIS3MDL::apply).
+    pub async fn apply(&mut self, config: &Config) -> Result<(), Error<DI::Error>> {
+        if !config.is_valid() {
+            return Err(Error::InvalidConfiguration);
+        }
+        self.write_control_registers(config.to_registers()).await
+    }
+
+    /// Writes CTRL_REG1 through CTRL_REG5 in a single burst and caches them.
+    async fn write_control_registers(&mut self, regs: [u8; 5]) -> Result<(), Error<DI::Error>> {
+        self.iface
+            .write_registers(registers::CTRL_REG1, &regs)
+            .await
+            .map_err(Error::Bus)?;
+        self.shadow.set_all(regs);
+        Ok(())
+    }
+
+    /// See [`LIS3MDL::effective_data_rate`](crate::LIS3MDL::effective_data_rate).
+    pub fn effective_data_rate(&self) -> Option<OutputDataRate> {
+        self.config().effective_data_rate()
+    }
+
+    /// See [`LIS3MDL::read_config`](crate::LIS3MDL::read_config).
+    pub async fn read_config(&mut self) -> Result<Config, Error<DI::Error>> {
+        self.resync().await?;
+        Ok(self.config())
+    }
+
+    /// See [`LIS3MDL::config`](crate::LIS3MDL::config).
+    pub fn config(&self) -> Config {
+        self.shadow.config()
+    }
+
+    /// See [`LIS3MDL::resync`](crate::LIS3MDL::resync).
+    pub async fn resync(&mut self) -> Result<(), Error<DI::Error>> {
+        let mut regs = [0; 5];
+        self.iface
+            .read_registers(registers::CTRL_REG1, &mut regs)
+            .await
+            .map_err(Error::Bus)?;
+        self.shadow.set_all(regs);
+        Ok(())
+    }
+
+    /// See [`LIS3MDL::set_operating_mode`](crate::LIS3MDL::set_operating_mode).
+    pub async fn set_operating_mode(
+        &mut self,
+        mode: OperatingMode,
+    ) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg3::from_bits(self.shadow.get(registers::CTRL_REG3));
+        reg.set_operating_mode(mode);
+        self.set_register(registers::CTRL_REG3, reg.bits()).await
+    }
+
+    /// See [`LIS3MDL::set_low_power`](crate::LIS3MDL::set_low_power).
+    pub async fn set_low_power(&mut self, enabled: bool) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg3::from_bits(self.shadow.get(registers::CTRL_REG3));
+        reg.set_low_power(enabled);
+        self.set_register(registers::CTRL_REG3, reg.bits()).await
+    }
+
+    /// Alias for `set_operating_mode(OperatingMode::PowerDown)`.
+    pub async fn power_down(&mut self) -> Result<(), Error<DI::Error>> {
+        self.set_operating_mode(OperatingMode::PowerDown).await
+    }
+
+    /// See [`LIS3MDL::set_full_scale`](crate::LIS3MDL::set_full_scale).
+    pub async fn set_full_scale(&mut self, scale: FullScale) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg2::from_bits(0);
+        reg.set_full_scale(scale);
+        self.set_register(registers::CTRL_REG2, reg.bits()).await
+    }
+
+    /// See [`LIS3MDL::set_xy_mode_and_data_rate`](crate::LIS3MDL::set_xy_mode_and_data_rate).
+    pub async fn set_xy_mode_and_data_rate(
+        &mut self,
+        mode: AxisMode,
+        odr: OutputDataRate,
+    ) -> Result<(), Error<DI::Error>> {
+        if matches!(odr.required_xy_mode(), Some(required) if required != mode) {
+            return Err(Error::InvalidConfiguration);
+        }
+
+        let mut reg = CtrlReg1::from_bits(self.shadow.get(registers::CTRL_REG1));
+        reg.set_xy_mode(mode);
+        reg.set_data_rate(odr);
+        self.set_register(registers::CTRL_REG1, reg.bits()).await
+    }
+
+    /// See [`LIS3MDL::set_xy_mode`](crate::LIS3MDL::set_xy_mode).
+    pub async fn set_xy_mode(&mut self, mode: AxisMode) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg1::from_bits(self.shadow.get(registers::CTRL_REG1));
+        reg.set_xy_mode(mode);
+        self.set_register(registers::CTRL_REG1, reg.bits()).await
+    }
+
+    /// See [`LIS3MDL::set_z_mode`](crate::LIS3MDL::set_z_mode).
+    pub async fn set_z_mode(&mut self, mode: AxisMode) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg4::from_bits(0);
+        reg.set_z_mode(mode);
+        self.set_register(registers::CTRL_REG4, reg.bits()).await
+    }
+
+    /// See [`LIS3MDL::set_data_rate`](crate::LIS3MDL::set_data_rate).
+    pub async fn set_data_rate(&mut self, odr: OutputDataRate) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg1::from_bits(self.shadow.get(registers::CTRL_REG1));
+        reg.set_data_rate(odr);
+        self.set_register(registers::CTRL_REG1, reg.bits()).await
+    }
+
+    /// See [`LIS3MDL::set_temperature_enabled`](crate::LIS3MDL::set_temperature_enabled).
+    pub async fn set_temperature_enabled(&mut self, enabled: bool) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg1::from_bits(self.shadow.get(registers::CTRL_REG1));
+        reg.set_temperature_enabled(enabled);
+        self.set_register(registers::CTRL_REG1, reg.bits()).await
+    }
+
+    /// See [`LIS3MDL::set_block_data_update`](crate::LIS3MDL::set_block_data_update).
+    pub async fn set_block_data_update(&mut self, enabled: bool) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg5::from_bits(self.shadow.get(registers::CTRL_REG5));
+        reg.set_block_data_update(enabled);
+        self.set_register(registers::CTRL_REG5, reg.bits()).await
+    }
+
+    /// See [`LIS3MDL::set_fast_read`](crate::LIS3MDL::set_fast_read).
+    pub async fn set_fast_read(&mut self, enabled: bool) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg5::from_bits(self.shadow.get(registers::CTRL_REG5));
+        reg.set_fast_read(enabled);
+        self.set_register(registers::CTRL_REG5, reg.bits()).await
+    }
+
+    /// See [`LIS3MDL::configure_interrupt`](crate::LIS3MDL::configure_interrupt).
+    pub async fn configure_interrupt(
+        &mut self,
+        config: &InterruptConfig,
+    ) -> Result<(), Error<DI::Error>> {
+        let threshold = threshold_to_raw(config.threshold, self.config().full_scale)
+            .ok_or(Error::InvalidConfiguration)?;
+        self.iface
+            .write_registers(registers::INT_THS_L, &threshold.to_le_bytes())
+            .await
+            .map_err(Error::Bus)?;
+        self.set_register(registers::INT_CFG, config.to_int_cfg().bits())
+            .await
+    }
+
+    /// See [`LIS3MDL::disable_interrupt`](crate::LIS3MDL::disable_interrupt).
+    pub async fn disable_interrupt(&mut self) -> Result<(), Error<DI::Error>> {
+        let mut reg = IntCfg::from_bits(self.read_register(registers::INT_CFG).await?);
+        reg.set_interrupt_enabled(false);
+        self.set_register(registers::INT_CFG, reg.bits()).await
+    }
+
+    /// See [`LIS3MDL::interrupt_threshold`](crate::LIS3MDL::interrupt_threshold).
+    pub async fn interrupt_threshold(&mut self) -> Result<f32, Error<DI::Error>> {
+        let mut values = [0; 2];
+        self.iface
+            .read_registers(registers::INT_THS_L, &mut values)
+            .await
+            .map_err(Error::Bus)?;
+        Ok(threshold_from_raw(
+            u16::from_le_bytes(values),
+            self.config().full_scale,
+        ))
+    }
+
+    /// See [`LIS3MDL::interrupt_source`](crate::LIS3MDL::interrupt_source).
+    pub async fn interrupt_source(&mut self) -> Result<IntSrc, Error<DI::Error>> {
+        self.read_register(registers::INT_SRC)
+            .await
+            .map(IntSrc::from_bits)
+    }
+
+    /// See [`LIS3MDL::arm_wake_on_field`](crate::LIS3MDL::arm_wake_on_field).
+    pub async fn arm_wake_on_field(
+        &mut self,
+        threshold: f32,
+        axes: Axes,
+    ) -> Result<(), Error<DI::Error>> {
+        threshold_to_raw(threshold, self.config().full_scale).ok_or(Error::InvalidConfiguration)?;
+
+        let saved = match self.wake {
+            Some(saved) => saved,
+            None => {
+                let int_cfg = self.read_register(registers::INT_CFG).await?;
+                let mut int_ths = [0; 2];
+                self.iface
+                    .read_registers(registers::INT_THS_L, &mut int_ths)
+                    .await
+                    .map_err(Error::Bus)?;
+                WakeState {
+                    ctrl_regs: self.shadow.registers(),
+                    int_cfg,
+                    int_ths,
+                }
+            }
+        };
+        self.wake = Some(saved);
+
+        let (config, interrupt) = saved.armed(threshold, axes);
+        // Switch to the new configuration before enabling the interrupt, and clear whatever
+        // a sample from the old one may have latched in between
+        self.apply(&config).await?;
+        self.configure_interrupt(&interrupt).await?;
+        self.interrupt_source().await?;
+        Ok(())
+    }
+
+    /// See [`LIS3MDL::disarm`](crate::LIS3MDL::disarm).
+    pub async fn disarm(&mut self) -> Result<(), Error<DI::Error>> {
+        let saved = match self.wake {
+            Some(saved) => saved,
+            None => return Ok(()),
+        };
+
+        self.write_control_registers(saved.ctrl_regs).await?;
+        self.set_register(registers::INT_CFG, saved.int_cfg).await?;
+        self.iface
+            .write_registers(registers::INT_THS_L, &saved.int_ths)
+            .await
+            .map_err(Error::Bus)?;
+        self.interrupt_source().await?;
+        self.wake = None;
+        Ok(())
+    }
+
+    /// Set one of the LIS3MDL's register to a certain value.
+    /// Writes to reserved or read-only registers are refused with `Error::InvalidRegister`.
+    pub async fn set_register(&mut self, reg: u8, value: u8) -> Result<(), Error<DI::Error>> {
+        if !registers::is_writable(reg) {
+            return Err(Error::InvalidRegister(reg));
+        }
+        self.iface
+            .write_register(reg, value)
+            .await
+            .map_err(Error::Bus)?;
+        self.shadow.update(reg, value);
+        Ok(())
+    }
+
+    /// Read one of the LIS3MDL's registers
+    pub async fn read_register(&mut self, reg: u8) -> Result<u8, Error<DI::Error>> {
+        self.iface.read_register(reg).await.map_err(Error::Bus)
+    }
+
+    /// Reads the latest data, returning `Error::NotReady` if any is not ready.
+    /// See [`LIS3MDL::read`](crate::LIS3MDL::read).
+    pub async fn read(&mut self) -> Result<(i16, i16, i16), Error<DI::Error>> {
+        self.read_with_status().await.map(|sample| sample.data)
+    }
+
+    /// See [`LIS3MDL::read_with_status`](crate::LIS3MDL::read_with_status).
+    pub async fn read_with_status(&mut self) -> Result<Sample, Error<DI::Error>> {
+        if self.shadow.fast_read() {
+            return Err(Error::InvalidConfiguration);
+        }
+        let status = self.status().await?;
+        if !status.zyxda() {
+            return Err(Error::NotReady);
+        }
+
+        let mut values = [0; 6];
+        self.iface
+            .read_registers(registers::OUT_X_L, &mut values)
+            .await
+            .map_err(Error::Bus)?;
+        Ok(Sample {
+            status,
+            data: decode_measurements(&values),
+        })
+    }
+
+    /// See [`LIS3MDL::status`](crate::LIS3MDL::status).
+    pub async fn status(&mut self) -> Result<StatusReg, Error<DI::Error>> {
+        self.read_register(registers::STATUS_REG)
+            .await
+            .map(StatusReg::from_bits)
+    }
+
+    /// See [`LIS3MDL::measure_once`](crate::LIS3MDL::measure_once).
+    pub async fn measure_once<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        timeout: Duration,
+    ) -> Result<(i16, i16, i16), Error<DI::Error>> {
+        // Single conversions only run at the DO rates, not the FAST_ODR ones
+        if self.config().data_rate.required_xy_mode().is_some() {
+            return Err(Error::InvalidConfiguration);
+        }
+
+        // Discard any sample left over from before, so it is not mistaken for the new one
+        match self.read().await {
+            Ok(_) | Err(Error::NotReady) => {}
+            Err(e) => return Err(e),
+        }
+
+        self.set_operating_mode(OperatingMode::SingleConversion)
+            .await?;
+
+        let sample = self.poll_read(delay, timeout).await?;
+
+        let mut reg = CtrlReg3::from_bits(self.shadow.get(registers::CTRL_REG3));
+        reg.set_operating_mode(OperatingMode::PowerDown);
+        self.shadow.update(registers::CTRL_REG3, reg.bits());
+
+        Ok(sample)
+    }
+
+    /// See [`LIS3MDL::wait_for_data`](crate::LIS3MDL::wait_for_data).
+    /// Unlike the blocking version, this sleeps until the pin goes high.
+    pub async fn wait_for_data<P: Wait>(
+        &mut self,
+        drdy: &mut P,
+    ) -> Result<(i16, i16, i16), Error<DI::Error>> {
+        if self.shadow.fast_read() {
+            return Err(Error::InvalidConfiguration);
+        }
+        drdy.wait_for_high()
+            .await
+            .map_err(|e| Error::Pin(e.kind()))?;
+
+        let mut values = [0; 6];
+        self.iface
+            .read_registers(registers::OUT_X_L, &mut values)
+            .await
+            .map_err(Error::Bus)?;
+        Ok(decode_measurements(&values))
+    }
+
+    /// See [`LIS3MDL::wait_for_threshold`](crate::LIS3MDL::wait_for_threshold).
+    pub async fn wait_for_threshold<P: Wait>(
+        &mut self,
+        int: &mut P,
+    ) -> Result<IntSrc, Error<DI::Error>> {
+        let reg = IntCfg::from_bits(self.read_register(registers::INT_CFG).await?);
+        if reg.active_high() {
+            int.wait_for_high().await
+        } else {
+            int.wait_for_low().await
+        }
+        .map_err(|e| Error::Pin(e.kind()))?;
+        self.interrupt_source().await
+    }
+
+    /// See [`LIS3MDL::self_test`](crate::LIS3MDL::self_test).
+    pub async fn self_test<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<SelfTestReport, Error<DI::Error>> {
+        // Restore the registers as they were, including bits `Config` does not cover
+        let saved = self.shadow.registers();
+        let report = self.run_self_test(delay, self.config()).await;
+        let restored = self.write_control_registers(saved).await;
+        let report = report?;
+        restored?;
+        Ok(report)
+    }
+
+    async fn run_self_test<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        saved: Config,
+    ) -> Result<SelfTestReport, Error<DI::Error>> {
+        self.apply(&self_test::config(saved)).await?;
+        delay.delay_us(self_test::SETTLE_US).await;
+        let off = self.sum_self_test_samples(delay).await?;
+
+        let mut reg = CtrlReg1::from_bits(self.shadow.get(registers::CTRL_REG1));
+        reg.set_self_test(true);
+        self.set_register(registers::CTRL_REG1, reg.bits()).await?;
+        delay.delay_us(self_test::SELF_TEST_SETTLE_US).await;
+        let on = self.sum_self_test_samples(delay).await?;
+
+        Ok(self_test::report(off, on))
+    }
+
+    /// Discards one sample, then sums the next `self_test::SAMPLES`.
+    async fn sum_self_test_samples<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<(i32, i32, i32), Error<DI::Error>> {
+        self.poll_read(delay, self_test::SAMPLE_TIMEOUT).await?;
+        let mut sum = (0, 0, 0);
+        for _ in 0..self_test::SAMPLES {
+            let sample = self.poll_read(delay, self_test::SAMPLE_TIMEOUT).await?;
+            self_test::accumulate(&mut sum, sample);
+        }
+        Ok(sum)
+    }
+
+    /// Waits for the next sample, polling every millisecond for at most `timeout`.
+    async fn poll_read<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        timeout: Duration,
+    ) -> Result<(i16, i16, i16), Error<DI::Error>> {
+        let mut waited = Duration::from_millis(0);
+        loop {
+            match self.read().await {
+                Err(Error::NotReady) if waited < timeout => {
+                    delay.delay_us(SINGLE_CONVERSION_POLL_US).await;
+                    waited += Duration::from_micros(SINGLE_CONVERSION_POLL_US as u64);
+                }
+                Err(Error::NotReady) => return Err(Error::Timeout),
+                result => return result,
+            }
+        }
+    }
+
+    /// See [`LIS3MDL::read_temperature`](crate::LIS3MDL::read_temperature).
+    pub async fn read_temperature(&mut self) -> Result<f32, Error<DI::Error>> {
+        let mut values = [0; 2];
+        self.iface
+            .read_registers(registers::TEMP_OUT_L, &mut values)
+            .await
+            .map_err(Error::Bus)?;
+        Ok(to_celsius(i16::from_le_bytes(values)))
+    }
+
+    /// See [`LIS3MDL::read_fast`](crate::LIS3MDL::read_fast).
+    pub async fn read_fast(&mut self) -> Result<(i8, i8, i8), Error<DI::Error>> {
+        if !self.shadow.fast_read() {
+            return Err(Error::InvalidConfiguration);
+        }
+        if !StatusReg::from_bits(self.read_register(registers::STATUS_REG).await?).zyxda() {
+            return Err(Error::NotReady);
+        }
+
+        let mut values = [0; 3];
+        self.iface
+            .read_registers(registers::OUT_X_H, &mut values)
+            .await
+            .map_err(Error::Bus)?;
+        Ok((values[0] as i8, values[1] as i8, values[2] as i8))
+    }
+
+    /// See [`LIS3MDL::read_gauss`](crate::LIS3MDL::read_gauss).
+    pub async fn read_gauss(&mut self) -> Result<MagneticField, Error<DI::Error>> {
+        let raw = self.read().await?;
+        Ok(to_field(raw, self.config().full_scale, 1.0))
+    }
+
+    /// See [`LIS3MDL::read_microtesla`](crate::LIS3MDL::read_microtesla).
+    pub async fn read_microtesla(&mut self) -> Result<MagneticField, Error<DI::Error>> {
+        let raw = self.read().await?;
+        Ok(to_field(raw, self.config().full_scale, 100.0))
+    }
+
+    /// See [`LIS3MDL::read_tesla`](crate::LIS3MDL::read_tesla).
+    pub async fn read_tesla(&mut self) -> Result<MagneticField, Error<DI::Error>> {
+        let raw = self.read().await?;
+        Ok(to_field(raw, self.config().full_scale, 1e-4))
+    }
+
+    /// See [`LIS3MDL::read_milligauss`](crate::LIS3MDL::read_milligauss).
+    pub async fn read_milligauss(&mut self) -> Result<MagneticField<i32>, Error<DI::Error>> {
+        let raw = self.read().await?;
+        Ok(to_milligauss(raw, self.config().full_scale))
+    }
+}
+
+async fn read_who_am_i<I: I2c>(i2c: &mut I, address: u8) -> Result<u8, I::Error> {
+    let mut resp = [0];
+    i2c.write_read(address, &[registers::WHO_AM_I], &mut resp)
+        .await?;
+    Ok(resp[0])
+}
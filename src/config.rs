@@ -0,0 +1,232 @@
+use crate::interrupt::WakeState;
+use crate::registers::{self, CtrlReg1, CtrlReg2, CtrlReg3, CtrlReg4, CtrlReg5};
+use crate::{AxisMode, FullScale, OperatingMode, OutputDataRate};
+
+/// The complete configuration held in CTRL_REG1 through CTRL_REG5.
+///
+/// The output endianness (BLE) and SPI wire mode (SIM) are not part of the configuration,
+/// as the driver relies on them being left at their little-endian, 4-wire defaults.
+/// `Config::default()` is the configuration the LIS3MDL powers up in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub temperature_enabled: bool,
+    pub xy_mode: AxisMode,
+    pub data_rate: OutputDataRate,
+    pub self_test: bool,
+    pub full_scale: FullScale,
+    pub low_power: bool,
+    pub operating_mode: OperatingMode,
+    pub z_mode: AxisMode,
+    pub fast_read: bool,
+    pub block_data_update: bool,
+}
+
+impl Config {
+    /// Decodes the values of CTRL_REG1 through CTRL_REG5, in that order.
+    pub fn from_registers(regs: [u8; 5]) -> Self {
+        let ctrl1 = CtrlReg1::from_bits(regs[0]);
+        let ctrl2 = CtrlReg2::from_bits(regs[1]);
+        let ctrl3 = CtrlReg3::from_bits(regs[2]);
+        let ctrl4 = CtrlReg4::from_bits(regs[3]);
+        let ctrl5 = CtrlReg5::from_bits(regs[4]);
+
+        Config {
+            temperature_enabled: ctrl1.temperature_enabled(),
+            xy_mode: ctrl1.xy_mode(),
+            data_rate: ctrl1.data_rate(),
+            self_test: ctrl1.self_test(),
+            full_scale: ctrl2.full_scale(),
+            low_power: ctrl3.low_power(),
+            operating_mode: ctrl3.operating_mode(),
+            z_mode: ctrl4.z_mode(),
+            fast_read: ctrl5.fast_read(),
+            block_data_update: ctrl5.block_data_update(),
+        }
+    }
+
+    /// Whether the device can run this configuration.
+    /// A FAST_ODR `data_rate` only works together with the `xy_mode` it requires.
+    pub fn is_valid(&self) -> bool {
+        !matches!(self.data_rate.required_xy_mode(), Some(mode) if mode != self.xy_mode)
+    }
+
+    /// The rate at which new samples are produced, or `None` if the device
+    /// is not continuously converting. Low-power mode forces 0.625 Hz.
+    pub fn effective_data_rate(&self) -> Option<OutputDataRate> {
+        match self.operating_mode {
+            OperatingMode::ContinuousConversion if self.low_power => {
+                Some(OutputDataRate::MilliHz625)
+            }
+            OperatingMode::ContinuousConversion => Some(self.data_rate),
+            _ => None,
+        }
+    }
+
+    /// Encodes the values of CTRL_REG1 through CTRL_REG5, in that order.
+    pub fn to_registers(&self) -> [u8; 5] {
+        let mut ctrl1 = CtrlReg1::from_bits(0);
+        ctrl1.set_temperature_enabled(self.temperature_enabled);
+        ctrl1.set_xy_mode(self.xy_mode);
+        ctrl1.set_data_rate(self.data_rate);
+        ctrl1.set_self_test(self.self_test);
+
+        let mut ctrl2 = CtrlReg2::from_bits(0);
+        ctrl2.set_full_scale(self.full_scale);
+
+        let mut ctrl3 = CtrlReg3::from_bits(0);
+        ctrl3.set_low_power(self.low_power);
+        ctrl3.set_operating_mode(self.operating_mode);
+
+        let mut ctrl4 = CtrlReg4::from_bits(0);
+        ctrl4.set_z_mode(self.z_mode);
+
+        let mut ctrl5 = CtrlReg5::from_bits(0);
+        ctrl5.set_fast_read(self.fast_read);
+        ctrl5.set_block_data_update(self.block_data_update);
+
+        [
+            ctrl1.bits(),
+            ctrl2.bits(),
+            ctrl3.bits(),
+            ctrl4.bits(),
+            ctrl5.bits(),
+        ]
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::from_registers([
+            CtrlReg1::default().bits(),
+            CtrlReg2::default().bits(),
+            CtrlReg3::default().bits(),
+            CtrlReg4::default().bits(),
+            CtrlReg5::default().bits(),
+        ])
+    }
+}
+
+/// Everything the driver remembers about the device: its copy of the control registers
+/// and any armed wake-on-field. See [`LIS3MDL::release`](crate::LIS3MDL::release).
+#[derive(Debug, Clone, Copy)]
+pub struct State {
+    pub(crate) shadow: Shadow,
+    pub(crate) wake: Option<WakeState>,
+}
+
+/// The driver's copy of CTRL_REG1 through CTRL_REG5, as last written to or read from the device.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Shadow([u8; 5]);
+
+impl Shadow {
+    fn index(reg: u8) -> Option<usize> {
+        match reg {
+            registers::CTRL_REG1..=registers::CTRL_REG5 => {
+                Some((reg - registers::CTRL_REG1) as usize)
+            }
+            _ => None,
+        }
+    }
+
+    /// The cached value of one of the control registers.
+    pub(crate) fn get(&self, reg: u8) -> u8 {
+        self.0[Self::index(reg).expect("not a control register")]
+    }
+
+    /// Records a value written to `reg`, ignoring registers that are not cached.
+    /// Rebooting or soft resetting through CTRL_REG2 puts every register back to its default.
+    pub(crate) fn update(&mut self, reg: u8, value: u8) {
+        if reg == registers::CTRL_REG2 {
+            let ctrl2 = CtrlReg2::from_bits(value);
+            if ctrl2.reboot() || ctrl2.soft_reset() {
+                *self = Shadow::default();
+                return;
+            }
+        }
+        if let Some(i) = Self::index(reg) {
+            self.0[i] = value;
+        }
+    }
+
+    pub(crate) fn registers(&self) -> [u8; 5] {
+        self.0
+    }
+
+    pub(crate) fn set_all(&mut self, regs: [u8; 5]) {
+        self.0 = regs;
+    }
+
+    /// Whether fast read is enabled, so that bursts from OUT_X_L skip the low bytes.
+    pub(crate) fn fast_read(&self) -> bool {
+        CtrlReg5::from_bits(self.get(registers::CTRL_REG5)).fast_read()
+    }
+
+    pub(crate) fn config(&self) -> Config {
+        Config::from_registers(self.0)
+    }
+}
+
+/// Assumes the device is in its power-on state.
+impl Default for Shadow {
+    fn default() -> Self {
+        Shadow([
+            CtrlReg1::default().bits(),
+            CtrlReg2::default().bits(),
+            CtrlReg3::default().bits(),
+            CtrlReg4::default().bits(),
+            CtrlReg5::default().bits(),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reset_through_ctrl_reg2_resets_the_shadow() {
+        for &value in [0b0000_0100, 0b0000_1000].iter() {
+            let mut shadow = Shadow::default();
+            shadow.update(registers::CTRL_REG1, 0xFE);
+            shadow.update(registers::CTRL_REG5, 0x40);
+            shadow.update(registers::CTRL_REG2, 0b0110_0000 | value);
+            assert_eq!(shadow.config(), Config::default());
+        }
+
+        let mut shadow = Shadow::default();
+        shadow.update(registers::CTRL_REG1, 0xFE);
+        shadow.update(registers::CTRL_REG2, 0b0110_0000);
+        assert_eq!(shadow.get(registers::CTRL_REG1), 0xFE);
+        assert_eq!(shadow.get(registers::CTRL_REG2), 0b0110_0000);
+    }
+
+    #[test]
+    fn fast_data_rates_are_only_valid_with_their_xy_mode() {
+        let rates = [
+            (OutputDataRate::Hz155, AxisMode::UltraPerformance),
+            (OutputDataRate::Hz300, AxisMode::HighPerformance),
+            (OutputDataRate::Hz560, AxisMode::MediumPerformance),
+            (OutputDataRate::Hz1000, AxisMode::LowPower),
+        ];
+        for &(data_rate, required) in rates.iter() {
+            for &xy_mode in [
+                AxisMode::LowPower,
+                AxisMode::MediumPerformance,
+                AxisMode::HighPerformance,
+                AxisMode::UltraPerformance,
+            ]
+            .iter()
+            {
+                let config = Config {
+                    xy_mode,
+                    data_rate,
+                    ..Config::default()
+                };
+                assert_eq!(config.is_valid(), xy_mode == required);
+                if config.is_valid() {
+                    assert_eq!(Config::from_registers(config.to_registers()), config);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,22 @@
+/// Everything that can go wrong when talking to the LIS3MDL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The underlying bus returned an error.
+    Bus(E),
+    /// A DRDY or INT pin could not be read.
+    Pin(embedded_hal::digital::ErrorKind),
+    /// The WHO_AM_I register did not identify the device as a LIS3MDL.
+    /// Holds the value that was read instead.
+    WrongDeviceId(u8),
+    /// No new measurement is available yet. This is not a failure,
+    /// and the read can be retried immediately.
+    NotReady,
+    /// The device did not produce a measurement in time.
+    Timeout,
+    /// The register is reserved or read-only and cannot be written.
+    InvalidRegister(u8),
+    /// The requested configuration is not supported by the device.
+    InvalidConfiguration,
+    /// The device failed its self test.
+    SelfTestFailed,
+}
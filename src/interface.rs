@@ -0,0 +1,169 @@
+//! Bus backends that the LIS3MDL driver can talk through.
+
+use embedded_hal::i2c::{self, I2c};
+use embedded_hal::spi::{Operation, SpiDevice};
+
+/// Set on the I2C sub-address to auto-increment the register address during multi-byte accesses.
+const I2C_AUTO_INCREMENT: u8 = 0b1000_0000;
+
+/// Set on the first byte of an SPI transaction to read instead of write.
+const SPI_READ: u8 = 0b1000_0000;
+/// Set on the first byte of an SPI transaction to auto-increment the register address.
+const SPI_MULTI: u8 = 0b0100_0000;
+
+/// Register level access to the LIS3MDL over some bus.
+///
+/// The driver is written entirely in terms of this trait, so implementing it is all
+/// that is needed to run the LIS3MDL over a new transport, a shared bus or a mock.
+/// Register addresses are the plain addresses from [`registers`](crate::registers);
+/// any bus specific framing (like the SPI read bit) is the implementation's job.
+pub trait Interface {
+    type Error;
+
+    /// Set one of the LIS3MDL's registers to a certain value
+    fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Self::Error>;
+
+    /// Burst write `values` to consecutive registers, starting at `reg`, in a single transaction
+    fn write_registers(&mut self, reg: u8, values: &[u8]) -> Result<(), Self::Error>;
+
+    /// Burst read consecutive registers, starting at `reg`, into `buf`
+    fn read_registers(&mut self, reg: u8, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Read one of the LIS3MDL's registers
+    fn read_register(&mut self, reg: u8) -> Result<u8, Self::Error> {
+        let mut resp = [0];
+        self.read_registers(reg, &mut resp)?;
+        Ok(resp[0])
+    }
+}
+
+impl<T: Interface + ?Sized> Interface for &mut T {
+    type Error = T::Error;
+
+    fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Self::Error> {
+        T::write_register(self, reg, value)
+    }
+
+    fn write_registers(&mut self, reg: u8, values: &[u8]) -> Result<(), Self::Error> {
+        T::write_registers(self, reg, values)
+    }
+
+    fn read_registers(&mut self, reg: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        T::read_registers(self, reg, buf)
+    }
+
+    fn read_register(&mut self, reg: u8) -> Result<u8, Self::Error> {
+        T::read_register(self, reg)
+    }
+}
+
+/// I2C backend for the LIS3MDL.
+///
+/// Errors are passed through from the bus untouched, so they implement
+/// [`embedded_hal::i2c::Error`] and `kind()` tells a NACK apart from arbitration loss.
+pub struct I2cInterface<I> {
+    pub(crate) i2c: I,
+    pub(crate) address: u8,
+}
+
+impl<I> I2cInterface<I> {
+    /// Creates an I2C backend talking to the LIS3MDL at the given 7-bit address.
+    pub fn new(i2c: I, address: u8) -> Self {
+        I2cInterface { i2c, address }
+    }
+
+    /// Releases the underlying bus.
+    pub fn destroy(self) -> I {
+        self.i2c
+    }
+}
+
+impl<I: I2c> Interface for I2cInterface<I> {
+    type Error = I::Error;
+
+    fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Self::Error> {
+        self.i2c.write(self.address, &[reg, value])
+    }
+
+    fn write_registers(&mut self, reg: u8, values: &[u8]) -> Result<(), Self::Error> {
+        let sub_address = i2c_sub_address(reg, values.len());
+        self.i2c.transaction(
+            self.address,
+            &mut [
+                i2c::Operation::Write(&[sub_address]),
+                i2c::Operation::Write(values),
+            ],
+        )
+    }
+
+    fn read_registers(&mut self, reg: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let sub_address = i2c_sub_address(reg, buf.len());
+        self.i2c.write_read(self.address, &[sub_address], buf)
+    }
+}
+
+/// 4-wire SPI backend for the LIS3MDL.
+/// Chip select is managed by the [`SpiDevice`] and asserted for the duration of every transaction.
+pub struct SpiInterface<S> {
+    pub(crate) spi: S,
+}
+
+impl<S> SpiInterface<S> {
+    /// Creates an SPI backend on top of a device that owns the LIS3MDL's chip select.
+    pub fn new(spi: S) -> Self {
+        SpiInterface { spi }
+    }
+
+    /// Releases the underlying SPI device.
+    pub fn destroy(self) -> S {
+        self.spi
+    }
+}
+
+impl<S: SpiDevice> Interface for SpiInterface<S> {
+    type Error = S::Error;
+
+    fn write_register(&mut self, reg: u8, value: u8) -> Result<(), Self::Error> {
+        self.spi.write(&[reg, value])
+    }
+
+    fn write_registers(&mut self, reg: u8, values: &[u8]) -> Result<(), Self::Error> {
+        let command = spi_write_command(reg, values.len());
+        self.spi
+            .transaction(&mut [Operation::Write(&[command]), Operation::Write(values)])
+    }
+
+    fn read_registers(&mut self, reg: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let command = spi_read_command(reg, buf.len());
+        self.spi
+            .transaction(&mut [Operation::Write(&[command]), Operation::Read(buf)])
+    }
+}
+
+/// The sub-address of an I2C transaction reading or writing `len` registers starting at `reg`.
+/// Unlike some of its siblings, the LIS3MDL only auto-increments when the MSB is set.
+pub(crate) fn i2c_sub_address(reg: u8, len: usize) -> u8 {
+    if len > 1 {
+        reg | I2C_AUTO_INCREMENT
+    } else {
+        reg
+    }
+}
+
+/// The first byte of an SPI transaction writing `len` registers starting at `reg`.
+pub(crate) fn spi_write_command(reg: u8, len: usize) -> u8 {
+    if len > 1 {
+        reg | SPI_MULTI
+    } else {
+        reg
+    }
+}
+
+/// The first byte of an SPI transaction reading `len` registers starting at `reg`.
+pub(crate) fn spi_read_command(reg: u8, len: usize) -> u8 {
+    let mut command = reg | SPI_READ;
+    if len > 1 {
+        command |= SPI_MULTI;
+    }
+    command
+}
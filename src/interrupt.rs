@@ -0,0 +1,154 @@
+use crate::registers::IntCfg;
+use crate::{AxisMode, Config, FullScale, OperatingMode, OutputDataRate};
+
+/// The level the INT pin is driven to while an interrupt is active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+    ActiveHigh,
+    ActiveLow,
+}
+
+/// Configuration of the threshold interrupt, see [`LIS3MDL::configure_interrupt`](crate::LIS3MDL::configure_interrupt).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InterruptConfig {
+    pub x: bool,
+    pub y: bool,
+    pub z: bool,
+    pub polarity: Polarity,
+    /// When set, the interrupt stays active until INT_SRC is read,
+    /// otherwise it only lasts as long as the threshold is exceeded.
+    pub latched: bool,
+    /// The threshold in gauss. An axis triggers the interrupt when its
+    /// measurement goes above `threshold` or below `-threshold`.
+    pub threshold: f32,
+}
+
+impl InterruptConfig {
+    pub(crate) fn to_int_cfg(self) -> IntCfg {
+        let mut reg = IntCfg::default();
+        reg.set_x_enabled(self.x);
+        reg.set_y_enabled(self.y);
+        reg.set_z_enabled(self.z);
+        reg.set_active_high(self.polarity == Polarity::ActiveHigh);
+        reg.set_latched(self.latched);
+        reg.set_interrupt_enabled(self.x || self.y || self.z);
+        reg
+    }
+}
+
+/// Converts a threshold in gauss to the value of INT_THS at the given full scale,
+/// or `None` if it is negative or beyond the 15 bit range of the register.
+pub(crate) fn threshold_to_raw(gauss: f32, scale: FullScale) -> Option<u16> {
+    let raw = gauss * scale.sensitivity() as f32;
+    if raw >= 0.0 && raw <= i16::MAX as f32 {
+        Some(raw as u16)
+    } else {
+        None
+    }
+}
+
+/// Converts the value of INT_THS to gauss at the given full scale.
+pub(crate) fn threshold_from_raw(raw: u16, scale: FullScale) -> f32 {
+    (raw & 0x7FFF) as f32 / scale.sensitivity() as f32
+}
+
+/// A selection of axes, see [`LIS3MDL::arm_wake_on_field`](crate::LIS3MDL::arm_wake_on_field).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Axes {
+    pub x: bool,
+    pub y: bool,
+    pub z: bool,
+}
+
+impl Axes {
+    pub const ALL: Axes = Axes {
+        x: true,
+        y: true,
+        z: true,
+    };
+}
+
+/// What wake-on-field changed, so disarming can put it back.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WakeState {
+    /// CTRL_REG1 through CTRL_REG5.
+    pub(crate) ctrl_regs: [u8; 5],
+    pub(crate) int_cfg: u8,
+    pub(crate) int_ths: [u8; 2],
+}
+
+impl WakeState {
+    /// The configuration to wake on `threshold` gauss on `axes`, converting as slowly
+    /// as the device can while keeping the interrupt polarity and full scale of before.
+    pub(crate) fn armed(&self, threshold: f32, axes: Axes) -> (Config, InterruptConfig) {
+        let config = Config {
+            temperature_enabled: false,
+            xy_mode: AxisMode::LowPower,
+            data_rate: OutputDataRate::MilliHz625,
+            self_test: false,
+            low_power: true,
+            operating_mode: OperatingMode::ContinuousConversion,
+            z_mode: AxisMode::LowPower,
+            ..Config::from_registers(self.ctrl_regs)
+        };
+        let polarity = if IntCfg::from_bits(self.int_cfg).active_high() {
+            Polarity::ActiveHigh
+        } else {
+            Polarity::ActiveLow
+        };
+        let interrupt = InterruptConfig {
+            x: axes.x,
+            y: axes.y,
+            z: axes.z,
+            polarity,
+            latched: true,
+            threshold,
+        };
+        (config, interrupt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold_converts_with_full_scale_sensitivity() {
+        assert_eq!(threshold_to_raw(0.0, FullScale::Four), Some(0));
+        assert_eq!(threshold_to_raw(1.0, FullScale::Four), Some(6842));
+        assert_eq!(threshold_to_raw(2.0, FullScale::Sixteen), Some(3422));
+        assert_eq!(threshold_to_raw(4.0, FullScale::Four), Some(27368));
+        assert_eq!(threshold_from_raw(6842, FullScale::Four), 1.0);
+    }
+
+    #[test]
+    fn threshold_rejects_values_outside_int_ths() {
+        assert_eq!(threshold_to_raw(-0.5, FullScale::Four), None);
+        assert_eq!(threshold_to_raw(4.78, FullScale::Four), Some(32704));
+        assert_eq!(threshold_to_raw(4.8, FullScale::Four), None);
+        assert_eq!(threshold_to_raw(20.0, FullScale::Sixteen), None);
+    }
+
+    #[test]
+    fn interrupt_config_encodes_int_cfg() {
+        let config = InterruptConfig {
+            x: true,
+            y: false,
+            z: true,
+            polarity: Polarity::ActiveHigh,
+            latched: true,
+            threshold: 1.0,
+        };
+        assert_eq!(config.to_int_cfg().bits(), 0b1010_1101);
+
+        let config = InterruptConfig {
+            x: false,
+            y: false,
+            z: false,
+            polarity: Polarity::ActiveLow,
+            latched: false,
+            threshold: 1.0,
+        };
+        assert_eq!(config.to_int_cfg().bits(), 0b0000_1010);
+    }
+}
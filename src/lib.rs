@@ -1,14 +1,121 @@
+//! Driver for the LIS3MDL magnetometer over I2C or SPI.
+//!
+//! # Sharing a bus
+//!
+//! The driver owns whatever it is given, so to put the LIS3MDL on the same bus as
+//! other peripherals give it a bus proxy, such as the ones from
+//! [embedded-hal-bus](https://crates.io/crates/embedded-hal-bus).
+//! Pick the proxy to match how the bus is shared: `RefCellDevice` within a single
+//! thread, `CriticalSectionDevice` or `AtomicDevice` across interrupts.
+//!
+//! ```
+//! # use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+//! use core::cell::RefCell;
+//! use embedded_hal::i2c::I2c;
+//! use embedded_hal_bus::i2c::RefCellDevice;
+//! use lis3mdl::{SlaveAddr, LIS3MDL};
+//!
+//! // Stand-in for an accelerometer driver living on the same bus.
+//! struct Accelerometer<I>(I);
+//!
+//! impl<I: I2c> Accelerometer<I> {
+//!     fn who_am_i(&mut self) -> Result<u8, I::Error> {
+//!         let mut id = [0];
+//!         self.0.write_read(0x6B, &[0x0F], &mut id)?;
+//!         Ok(id[0])
+//!     }
+//! }
+//!
+//! # let i2c = Mock::new(&[
+//! #     Transaction::write_read(0x1E, vec![0x0F], vec![0x3D]),
+//! #     Transaction::write_read(0x1E, vec![0xA0], vec![0x10, 0x00, 0x03, 0x00, 0x00]),
+//! #     Transaction::write_read(0x6B, vec![0x0F], vec![0x69]),
+//! #     Transaction::write_read(0x1E, vec![0x0F], vec![0x3D]),
+//! # ]);
+//! # let mut mock = i2c.clone();
+//! let bus = RefCell::new(i2c);
+//!
+//! let mut magnetometer =
+//!     LIS3MDL::new_with_address_verified(RefCellDevice::new(&bus), SlaveAddr::Sa1High).unwrap();
+//! let mut accelerometer = Accelerometer(RefCellDevice::new(&bus));
+//!
+//! assert_eq!(accelerometer.who_am_i().unwrap(), 0x69);
+//! assert_eq!(magnetometer.who_am_i().unwrap(), 0x3D);
+//! # mock.done();
+//! ```
+
 #![no_std]
 
+#[cfg(feature = "async")]
+pub mod asynch;
+mod config;
+mod error;
+pub mod interface;
+mod interrupt;
+#[cfg(feature = "linux")]
+pub mod linux;
+mod measurement;
+mod power;
 pub mod registers;
+mod self_test;
+
+use core::time::Duration;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{Error as _, InputPin};
+use embedded_hal::i2c::{Error as _, ErrorKind, I2c};
+use embedded_hal::spi::SpiDevice;
 
-use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+#[cfg(feature = "async")]
+pub use asynch::{AsyncInterface, LIS3MDLAsync};
+use config::Shadow;
+pub use config::{Config, State};
+pub use embedded_hal;
+pub use error::Error;
+pub use interface::{I2cInterface, Interface, SpiInterface};
+use interrupt::{threshold_from_raw, threshold_to_raw, WakeState};
+pub use interrupt::{Axes, InterruptConfig, Polarity};
+use measurement::{to_celsius, to_field, to_milligauss};
+pub use measurement::{MagneticField, Sample};
+pub use power::estimate_supply_current;
+pub use self_test::SelfTestReport;
+
+use registers::{CtrlReg1, CtrlReg2, CtrlReg3, CtrlReg4, CtrlReg5, IntCfg, IntSrc, StatusReg};
 
 const LIS3MDL_SA1_HIGH_ADDRESS: u8 = 0b0011110;
 const LIS3MDL_SA1_LOW_ADDRESS: u8 = 0b0011100;
 
 const LIS3MDL_WHO_ID: u8 = 0x3d;
 
+/// How often `measure_once` checks whether its conversion has finished.
+const SINGLE_CONVERSION_POLL_US: u32 = 1000;
+
+/// How often `wait_for_data` and `wait_for_threshold` check their pin.
+const PIN_POLL_US: u32 = 100;
+
+/// The I2C address of a LIS3MDL, which is selected by the level of its SA1 pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlaveAddr {
+    /// SA1 tied high (0x1E), the default on most breakout boards.
+    Sa1High,
+    /// SA1 tied low (0x1C).
+    Sa1Low,
+    /// An arbitrary 7-bit address, e.g. behind an address translator.
+    Raw(u8),
+}
+
+impl SlaveAddr {
+    /// The 7-bit I2C address.
+    pub fn addr(self) -> u8 {
+        match self {
+            SlaveAddr::Sa1High => LIS3MDL_SA1_HIGH_ADDRESS,
+            SlaveAddr::Sa1Low => LIS3MDL_SA1_LOW_ADDRESS,
+            SlaveAddr::Raw(address) => address,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OperatingMode {
     ContinuousConversion,
     SingleConversion,
@@ -23,8 +130,17 @@ impl OperatingMode {
             OperatingMode::PowerDown => 2,
         }
     }
+
+    fn from_bitcode(code: u8) -> Self {
+        match code & 0b11 {
+            0 => OperatingMode::ContinuousConversion,
+            1 => OperatingMode::SingleConversion,
+            _ => OperatingMode::PowerDown,
+        }
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FullScale {
     Four,
     Eight,
@@ -41,8 +157,18 @@ impl FullScale {
             FullScale::Sixteen => 3,
         }
     }
+
+    fn from_bitcode(code: u8) -> Self {
+        match code & 0b11 {
+            0 => FullScale::Four,
+            1 => FullScale::Eight,
+            2 => FullScale::Twelve,
+            _ => FullScale::Sixteen,
+        }
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AxisMode {
     LowPower,
     MediumPerformance,
@@ -59,10 +185,19 @@ impl AxisMode {
             AxisMode::UltraPerformance => 3,
         }
     }
+
+    fn from_bitcode(code: u8) -> Self {
+        match code & 0b11 {
+            0 => AxisMode::LowPower,
+            1 => AxisMode::MediumPerformance,
+            2 => AxisMode::HighPerformance,
+            _ => AxisMode::UltraPerformance,
+        }
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OutputDataRate {
-    Fast,
     MilliHz625,
     MilliHz1250,
     MilliHz2500,
@@ -71,12 +206,19 @@ pub enum OutputDataRate {
     Hz20,
     Hz40,
     Hz80,
+    /// FAST_ODR in ultra-high-performance mode.
+    Hz155,
+    /// FAST_ODR in high-performance mode.
+    Hz300,
+    /// FAST_ODR in medium-performance mode.
+    Hz560,
+    /// FAST_ODR in low-power mode.
+    Hz1000,
 }
 
 impl OutputDataRate {
     fn to_bitcode(self) -> u8 {
         match self {
-            OutputDataRate::Fast => 1,
             OutputDataRate::MilliHz625 => 0,
             OutputDataRate::MilliHz1250 => 0b10,
             OutputDataRate::MilliHz2500 => 0b100,
@@ -85,139 +227,766 @@ impl OutputDataRate {
             OutputDataRate::Hz20 => 0b1010,
             OutputDataRate::Hz40 => 0b1100,
             OutputDataRate::Hz80 => 0b1110,
+            OutputDataRate::Hz155
+            | OutputDataRate::Hz300
+            | OutputDataRate::Hz560
+            | OutputDataRate::Hz1000 => 1,
         }
     }
-}
 
-pub struct LIS3MDL<E, I: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>> {
-    address: u8,
-    i2c: I,
-}
+    /// With FAST_ODR set, the data rate is determined by the x and y axis mode instead of the DO bits.
+    fn from_bitcode(code: u8, xy_mode: AxisMode) -> Self {
+        if code & 1 == 1 {
+            return match xy_mode {
+                AxisMode::UltraPerformance => OutputDataRate::Hz155,
+                AxisMode::HighPerformance => OutputDataRate::Hz300,
+                AxisMode::MediumPerformance => OutputDataRate::Hz560,
+                AxisMode::LowPower => OutputDataRate::Hz1000,
+            };
+        }
+        match code & 0b1110 {
+            0 => OutputDataRate::MilliHz625,
+            0b10 => OutputDataRate::MilliHz1250,
+            0b100 => OutputDataRate::MilliHz2500,
+            0b110 => OutputDataRate::Hz5,
+            0b1000 => OutputDataRate::Hz10,
+            0b1010 => OutputDataRate::Hz20,
+            0b1100 => OutputDataRate::Hz40,
+            _ => OutputDataRate::Hz80,
+        }
+    }
 
-impl<E, I: Clone + Read<Error = E> + Write<Error = E> + WriteRead<Error = E>> Clone for LIS3MDL<E, I> {
-    fn clone(&self) -> Self {
-        LIS3MDL {
-            address: self.address.clone(),
-            i2c: self.i2c.clone(),
+    /// The x and y axis mode a FAST_ODR rate requires, or `None` for the regular rates,
+    /// which work in any mode.
+    pub fn required_xy_mode(self) -> Option<AxisMode> {
+        match self {
+            OutputDataRate::Hz155 => Some(AxisMode::UltraPerformance),
+            OutputDataRate::Hz300 => Some(AxisMode::HighPerformance),
+            OutputDataRate::Hz560 => Some(AxisMode::MediumPerformance),
+            OutputDataRate::Hz1000 => Some(AxisMode::LowPower),
+            _ => None,
         }
     }
+
+    /// The output data rate in millihertz.
+    pub fn millihertz(self) -> u32 {
+        match self {
+            OutputDataRate::MilliHz625 => 625,
+            OutputDataRate::MilliHz1250 => 1_250,
+            OutputDataRate::MilliHz2500 => 2_500,
+            OutputDataRate::Hz5 => 5_000,
+            OutputDataRate::Hz10 => 10_000,
+            OutputDataRate::Hz20 => 20_000,
+            OutputDataRate::Hz40 => 40_000,
+            OutputDataRate::Hz80 => 80_000,
+            OutputDataRate::Hz155 => 155_000,
+            OutputDataRate::Hz300 => 300_000,
+            OutputDataRate::Hz560 => 560_000,
+            OutputDataRate::Hz1000 => 1_000_000,
+        }
+    }
+
+    /// The output data rate in hertz.
+    pub fn hertz(self) -> f32 {
+        self.millihertz() as f32 / 1000.0
+    }
+
+    /// The time between two samples.
+    pub fn period(self) -> Duration {
+        Duration::from_micros(1_000_000_000 / self.millihertz() as u64)
+    }
 }
 
-impl<E, I: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>> LIS3MDL<E, I> {
-    pub fn new(mut i2c: I) -> Result<Option<Self>, E> {
+pub struct LIS3MDL<DI> {
+    iface: DI,
+    shadow: Shadow,
+    wake: Option<WakeState>,
+}
+
+impl<I: I2c> LIS3MDL<I2cInterface<I>> {
+    pub fn new(mut i2c: I) -> Result<Self, Error<I::Error>> {
         // Get the correct address for the LIS3MDL that is being used
-        let address = if test_lism3mdl_addr(&mut i2c, LIS3MDL_SA1_HIGH_ADDRESS)? {
+        let first = match read_who_am_i(&mut i2c, LIS3MDL_SA1_HIGH_ADDRESS) {
+            Ok(id) => Some(id),
+            // Nothing answers here on boards with SA1 pulled low, so try the other address
+            Err(e) if matches!(e.kind(), ErrorKind::NoAcknowledge(_)) => None,
+            Err(e) => return Err(Error::Bus(e)),
+        };
+        let address = if first == Some(LIS3MDL_WHO_ID) {
             LIS3MDL_SA1_HIGH_ADDRESS
-        } else if test_lism3mdl_addr(&mut i2c, LIS3MDL_SA1_LOW_ADDRESS)? {
-            LIS3MDL_SA1_LOW_ADDRESS
         } else {
-            return Ok(None);
+            let id = match read_who_am_i(&mut i2c, LIS3MDL_SA1_LOW_ADDRESS) {
+                Ok(id) => id,
+                // If only the first address answered, what it answered with is the real problem
+                Err(e) => {
+                    return Err(match first {
+                        Some(id) if matches!(e.kind(), ErrorKind::NoAcknowledge(_)) => {
+                            Error::WrongDeviceId(id)
+                        }
+                        _ => Error::Bus(e),
+                    })
+                }
+            };
+            if id != LIS3MDL_WHO_ID {
+                return Err(Error::WrongDeviceId(id));
+            }
+            LIS3MDL_SA1_LOW_ADDRESS
+        };
+
+        let mut this = Self {
+            iface: I2cInterface::new(i2c, address),
+            shadow: Shadow::default(),
+            wake: None,
+        };
+        // Unlike the lsm6ds33 there is no incrementation to turn on,
+        // the interface asks for it on every multi-byte access instead
+        this.resync()?;
+
+        Ok(this)
+    }
+
+    /// Creates a driver for the LIS3MDL at a known address without touching the bus.
+    /// Use this instead of `new` when several sensors share a bus or probing is undesirable.
+    ///
+    /// Without touching the bus the driver cannot know how the device is configured, so it
+    /// assumes the power-on configuration. If the device may have been configured before
+    /// (say, by firmware that has since reset), call [`resync`](Self::resync) before using
+    /// the partial setters or the scaled reads, or they will work from the wrong full scale
+    /// and overwrite bits set earlier.
+    ///
+    /// Since `&mut I` is itself an I2C bus, the driver can also borrow the bus instead of
+    /// owning it. To borrow it anew for every use without losing what the driver knows about
+    /// the device, [`release`](Self::release) the driver and [`attach`](Self::attach) it again:
+    ///
+    /// ```
+    /// # use embedded_hal::i2c::I2c;
+    /// # use lis3mdl::{Error, I2cInterface, MagneticField, State, LIS3MDL};
+    /// fn sample<I: I2c>(bus: &mut I, state: &mut State) -> Result<MagneticField, Error<I::Error>> {
+    ///     let mut lis3mdl = LIS3MDL::attach(I2cInterface::new(bus, 0x1E), *state);
+    ///     let field = lis3mdl.read_gauss();
+    ///     *state = lis3mdl.release().1;
+    ///     field
+    /// }
+    /// ```
+    pub fn new_with_address(i2c: I, address: SlaveAddr) -> Self {
+        Self {
+            iface: I2cInterface::new(i2c, address.addr()),
+            shadow: Shadow::default(),
+            wake: None,
+        }
+    }
+
+    /// Like `new_with_address`, but checks the WHO_AM_I register first,
+    /// returning `Error::WrongDeviceId` if the device at `address` is not a LIS3MDL.
+    pub fn new_with_address_verified(i2c: I, address: SlaveAddr) -> Result<Self, Error<I::Error>> {
+        Self::new_with_interface(I2cInterface::new(i2c, address.addr()))
+    }
+
+    /// Destroys the driver, giving back the I2C bus.
+    pub fn destroy(self) -> I {
+        self.iface.destroy()
+    }
+}
+
+impl<S: SpiDevice> LIS3MDL<SpiInterface<S>> {
+    /// Creates a driver for a LIS3MDL wired over 4-wire SPI, returning `Error::WrongDeviceId`
+    /// if the device on the other end does not identify as a LIS3MDL.
+    pub fn new_spi(spi: S) -> Result<Self, Error<S::Error>> {
+        Self::new_with_interface(SpiInterface::new(spi))
+    }
+
+    /// Destroys the driver, giving back the SPI device.
+    pub fn destroy(self) -> S {
+        self.iface.destroy()
+    }
+}
+
+impl<DI: Interface> LIS3MDL<DI> {
+    /// Creates a driver on top of an arbitrary [`Interface`], returning `Error::WrongDeviceId`
+    /// if the device on the other end does not identify as a LIS3MDL.
+    /// The driver's copy of the control registers is read from the device, see [`resync`](Self::resync).
+    pub fn new_with_interface(mut iface: DI) -> Result<Self, Error<DI::Error>> {
+        let id = iface
+            .read_register(registers::WHO_AM_I)
+            .map_err(Error::Bus)?;
+        if id != LIS3MDL_WHO_ID {
+            return Err(Error::WrongDeviceId(id));
+        }
+
+        let mut this = Self {
+            iface,
+            shadow: Shadow::default(),
+            wake: None,
+        };
+        this.resync()?;
+        Ok(this)
+    }
+
+    /// Destroys the driver, giving back the interface it was built on.
+    pub fn into_interface(self) -> DI {
+        self.iface
+    }
+
+    /// Destroys the driver, giving back the interface it was built on along with
+    /// everything the driver remembers about the device. Pass both to
+    /// [`attach`](Self::attach) to carry on where the driver left off.
+    pub fn release(self) -> (DI, State) {
+        let state = State {
+            shadow: self.shadow,
+            wake: self.wake,
         };
+        (self.iface, state)
+    }
 
-        let this = Self { address, i2c };
-        // TODO: unsure if I have to turn on incrementation like I did for the lsm6ds33
+    /// Rebuilds a driver released with [`release`](Self::release), without touching the bus.
+    pub fn attach(iface: DI, state: State) -> Self {
+        Self {
+            iface,
+            shadow: state.shadow,
+            wake: state.wake,
+        }
+    }
 
-        Ok(Some(this))
+    /// Reads the WHO_AM_I register, which is 0x3D on a genuine LIS3MDL.
+    pub fn who_am_i(&mut self) -> Result<u8, Error<DI::Error>> {
+        self.read_register(registers::WHO_AM_I)
     }
 
-    /// Initialize the lis3mdl in high performance axis modes, continous conversion mode, 
+    /// Initialize the lis3mdl in high performance axis modes, continous conversion mode,
     /// and 10 Hz output data rate.
-    pub fn init_default(&mut self) -> Result<(), E> {
+    pub fn init_default(&mut self) -> Result<(), Error<DI::Error>> {
         self.set_xy_mode_and_data_rate(AxisMode::HighPerformance, OutputDataRate::Hz10)?;
         self.set_z_mode(AxisMode::HighPerformance)?;
         self.set_full_scale(FullScale::Four)?;
         self.set_operating_mode(OperatingMode::ContinuousConversion)
     }
 
+    /// Writes the whole configuration to CTRL_REG1 through CTRL_REG5 in a single
+    /// auto-incremented burst, so the device never runs with a partial configuration.
+    /// Returns `Error::InvalidConfiguration` for configurations the device cannot run, see [`Config::is_valid`].
+    pub fn apply(&mut self, config: &Config) -> Result<(), Error<DI::Error>> {
+        if !config.is_valid() {
+            return Err(Error::InvalidConfiguration);
+        }
+        self.write_control_registers(config.to_registers())
+    }
+
+    /// Writes CTRL_REG1 through CTRL_REG5 in a single burst and caches them.
+    fn write_control_registers(&mut self, regs: [u8; 5]) -> Result<(), Error<DI::Error>> {
+        self.iface
+            .write_registers(registers::CTRL_REG1, &regs)
+            .map_err(Error::Bus)?;
+        self.shadow.set_all(regs);
+        Ok(())
+    }
+
+    /// The rate at which new samples are produced in the current configuration,
+    /// or `None` if the device is not continuously converting.
+    /// Use [`OutputDataRate::hertz`] and [`OutputDataRate::period`] to get at the numbers.
+    pub fn effective_data_rate(&self) -> Option<OutputDataRate> {
+        self.config().effective_data_rate()
+    }
+
+    /// Reads back CTRL_REG1 through CTRL_REG5 and decodes the device's current configuration.
+    pub fn read_config(&mut self) -> Result<Config, Error<DI::Error>> {
+        self.resync()?;
+        Ok(self.config())
+    }
+
+    /// The configuration the driver last wrote or read, without touching the bus.
+    pub fn config(&self) -> Config {
+        self.shadow.config()
+    }
+
+    /// Re-reads the control registers into the driver's cached copy.
+    ///
+    /// The probing constructors read the control registers once, while `new_with_address`
+    /// assumes the power-on configuration. Afterwards the driver tracks every control
+    /// register it writes, so that partial updates are a single write. Call this if the
+    /// device may have been configured by someone else, or has reset behind the driver's
+    /// back (e.g. after a brown-out).
+    pub fn resync(&mut self) -> Result<(), Error<DI::Error>> {
+        let mut regs = [0; 5];
+        self.iface
+            .read_registers(registers::CTRL_REG1, &mut regs)
+            .map_err(Error::Bus)?;
+        self.shadow.set_all(regs);
+        Ok(())
+    }
+
     /// Sets the operating mode for the whole system. This is entirely different than setting the xy mode or z mode.
-    /// This overwrites the CTRL_REG3 register.
-    pub fn set_operating_mode(&mut self, mode: OperatingMode) -> Result<(), E> {
-        self.set_register(registers::CTRL_REG3, mode.to_bitcode())
+    /// Only the mode bits of the CTRL_REG3 register are overwritten, so low-power mode is left alone.
+    pub fn set_operating_mode(&mut self, mode: OperatingMode) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg3::from_bits(self.shadow.get(registers::CTRL_REG3));
+        reg.set_operating_mode(mode);
+        self.set_register(registers::CTRL_REG3, reg.bits())
+    }
+
+    /// Enables or disables low-power mode (the LP bit of CTRL_REG3), which forces the
+    /// data rate to 0.625 Hz and skips averaging, regardless of CTRL_REG1.
+    pub fn set_low_power(&mut self, enabled: bool) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg3::from_bits(self.shadow.get(registers::CTRL_REG3));
+        reg.set_low_power(enabled);
+        self.set_register(registers::CTRL_REG3, reg.bits())
     }
 
     /// Alias for `set_operating_mode(OperatingMode::PowerDown)`.
-    pub fn power_down(&mut self) -> Result<(), E> {
+    pub fn power_down(&mut self) -> Result<(), Error<DI::Error>> {
         self.set_operating_mode(OperatingMode::PowerDown)
     }
 
     /// Sets the full scale (in ± gauss) of the magnetometer.
     /// Overwrites the CTRL_REG2 register.
-    pub fn set_full_scale(&mut self, scale: FullScale) -> Result<(), E> {
-        self.set_register(registers::CTRL_REG2, scale.to_bitcode())
+    pub fn set_full_scale(&mut self, scale: FullScale) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg2::from_bits(0);
+        reg.set_full_scale(scale);
+        self.set_register(registers::CTRL_REG2, reg.bits())
     }
 
     /// Set the operative mode for the x and y axes as well as the output data rate of the sensor.
-    /// This function is faster than setting both individually, but will fully overwrite the CTRL_REG1 register.
+    /// This function is faster than setting both individually, and leaves the
+    /// temperature sensor and self test bits of the CTRL_REG1 register untouched.
+    /// Returns `Error::InvalidConfiguration` if `odr` is a FAST_ODR rate that `mode` does not produce.
     pub fn set_xy_mode_and_data_rate(
         &mut self,
         mode: AxisMode,
         odr: OutputDataRate,
-    ) -> Result<(), E> {
-        self.set_register(
-            registers::CTRL_REG1,
-            (mode.to_bitcode() << 5) | (odr.to_bitcode() << 1),
-        )
+    ) -> Result<(), Error<DI::Error>> {
+        if matches!(odr.required_xy_mode(), Some(required) if required != mode) {
+            return Err(Error::InvalidConfiguration);
+        }
+
+        let mut reg = CtrlReg1::from_bits(self.shadow.get(registers::CTRL_REG1));
+        reg.set_xy_mode(mode);
+        reg.set_data_rate(odr);
+        self.set_register(registers::CTRL_REG1, reg.bits())
     }
 
     /// Sets the operative mode of the x and y axes while
     /// only overwriting the relevant bits of the CTRL_REG1 register.
-    pub fn set_xy_mode(&mut self, mode: AxisMode) -> Result<(), E> {
-        let cur = self.read_register(registers::CTRL_REG1)?;
-        self.set_register(registers::CTRL_REG1, cur & (0b10011111 & (mode.to_bitcode() << 5)))
+    /// The rest of the register comes from the driver's cached copy, see `resync`.
+    pub fn set_xy_mode(&mut self, mode: AxisMode) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg1::from_bits(self.shadow.get(registers::CTRL_REG1));
+        reg.set_xy_mode(mode);
+        self.set_register(registers::CTRL_REG1, reg.bits())
     }
 
     /// Sets the operative mode fo the z axis.
     /// Overwrites the CTRL_REG4 register.
-    pub fn set_z_mode(&mut self, mode: AxisMode) -> Result<(), E> {
-        self.set_register(registers::CTRL_REG4, mode.to_bitcode() << 2)
+    pub fn set_z_mode(&mut self, mode: AxisMode) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg4::from_bits(0);
+        reg.set_z_mode(mode);
+        self.set_register(registers::CTRL_REG4, reg.bits())
     }
 
     /// Sets the output data rate while
     /// only overwriting the relevant bits of the CTRL_REG1 register.
-    pub fn set_data_rate(&mut self, odr: OutputDataRate) -> Result<(), E> {
-        let cur = self.read_register(registers::CTRL_REG1)?;
-        self.set_register(registers::CTRL_REG1, cur & (0b11100001 & (odr.to_bitcode() << 1)))
+    /// The rest of the register comes from the driver's cached copy, see `resync`.
+    /// The FAST_ODR rates also switch the x and y axes to the mode they require.
+    pub fn set_data_rate(&mut self, odr: OutputDataRate) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg1::from_bits(self.shadow.get(registers::CTRL_REG1));
+        reg.set_data_rate(odr);
+        self.set_register(registers::CTRL_REG1, reg.bits())
     }
 
-    /// Set one of the LIS3MDL's register to a certain value
-    pub fn set_register(&mut self, reg: u8, value: u8) -> Result<(), E> {
-        self.i2c.write(self.address, &[reg, value])
+    /// Enables or disables the on-die temperature sensor,
+    /// only overwriting the relevant bit of the CTRL_REG1 register.
+    pub fn set_temperature_enabled(&mut self, enabled: bool) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg1::from_bits(self.shadow.get(registers::CTRL_REG1));
+        reg.set_temperature_enabled(enabled);
+        self.set_register(registers::CTRL_REG1, reg.bits())
+    }
+
+    /// Enables or disables block data update, only overwriting the relevant bit of the CTRL_REG5 register.
+    /// With it enabled the output registers are not updated until both bytes of
+    /// the previous sample have been read, so high and low bytes always belong together.
+    pub fn set_block_data_update(&mut self, enabled: bool) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg5::from_bits(self.shadow.get(registers::CTRL_REG5));
+        reg.set_block_data_update(enabled);
+        self.set_register(registers::CTRL_REG5, reg.bits())
+    }
+
+    /// Enables or disables fast read, only overwriting the relevant bit of the CTRL_REG5 register.
+    /// With it enabled burst reads skip the low bytes of the output, see `read_fast`.
+    pub fn set_fast_read(&mut self, enabled: bool) -> Result<(), Error<DI::Error>> {
+        let mut reg = CtrlReg5::from_bits(self.shadow.get(registers::CTRL_REG5));
+        reg.set_fast_read(enabled);
+        self.set_register(registers::CTRL_REG5, reg.bits())
+    }
+
+    /// Configures the threshold interrupt on the INT pin.
+    /// The threshold is converted using the full scale the driver last configured,
+    /// so set the full scale first. Returns `Error::InvalidConfiguration` if the
+    /// threshold is negative or does not fit the 15 bits of INT_THS, which at ±4 gauss
+    /// is slightly above the full scale (about 4.8 gauss).
+    pub fn configure_interrupt(
+        &mut self,
+        config: &InterruptConfig,
+    ) -> Result<(), Error<DI::Error>> {
+        let threshold = threshold_to_raw(config.threshold, self.config().full_scale)
+            .ok_or(Error::InvalidConfiguration)?;
+        self.iface
+            .write_registers(registers::INT_THS_L, &threshold.to_le_bytes())
+            .map_err(Error::Bus)?;
+        self.set_register(registers::INT_CFG, config.to_int_cfg().bits())
+    }
+
+    /// Disables the threshold interrupt, leaving the rest of its configuration alone.
+    pub fn disable_interrupt(&mut self) -> Result<(), Error<DI::Error>> {
+        let mut reg = IntCfg::from_bits(self.read_register(registers::INT_CFG)?);
+        reg.set_interrupt_enabled(false);
+        self.set_register(registers::INT_CFG, reg.bits())
+    }
+
+    /// Reads the current interrupt threshold in gauss.
+    pub fn interrupt_threshold(&mut self) -> Result<f32, Error<DI::Error>> {
+        let mut values = [0; 2];
+        self.iface
+            .read_registers(registers::INT_THS_L, &mut values)
+            .map_err(Error::Bus)?;
+        Ok(threshold_from_raw(
+            u16::from_le_bytes(values),
+            self.config().full_scale,
+        ))
+    }
+
+    /// Reads the INT_SRC register, telling which axes crossed the threshold in
+    /// which direction. Reading it clears a latched interrupt.
+    pub fn interrupt_source(&mut self) -> Result<IntSrc, Error<DI::Error>> {
+        self.read_register(registers::INT_SRC)
+            .map(IntSrc::from_bits)
+    }
+
+    /// Puts the device in its lowest-power continuous mode and latches the INT pin once
+    /// the field on any of `axes` goes beyond `threshold` gauss, e.g. for lid or door detection.
+    /// The configuration of before is remembered until [`disarm`](Self::disarm); arming again
+    /// only changes the threshold and axes. The interrupt polarity and full scale are kept.
+    pub fn arm_wake_on_field(
+        &mut self,
+        threshold: f32,
+        axes: Axes,
+    ) -> Result<(), Error<DI::Error>> {
+        threshold_to_raw(threshold, self.config().full_scale).ok_or(Error::InvalidConfiguration)?;
+
+        let saved = match self.wake {
+            Some(saved) => saved,
+            None => {
+                let int_cfg = self.read_register(registers::INT_CFG)?;
+                let mut int_ths = [0; 2];
+                self.iface
+                    .read_registers(registers::INT_THS_L, &mut int_ths)
+                    .map_err(Error::Bus)?;
+                WakeState {
+                    ctrl_regs: self.shadow.registers(),
+                    int_cfg,
+                    int_ths,
+                }
+            }
+        };
+        self.wake = Some(saved);
+
+        let (config, interrupt) = saved.armed(threshold, axes);
+        // Switch to the new configuration before enabling the interrupt, and clear whatever
+        // a sample from the old one may have latched in between
+        self.apply(&config)?;
+        self.configure_interrupt(&interrupt)?;
+        self.interrupt_source()?;
+        Ok(())
+    }
+
+    /// Undoes [`arm_wake_on_field`](Self::arm_wake_on_field), clearing a latched interrupt
+    /// and restoring the configuration and interrupt settings of before.
+    /// Does nothing if wake-on-field is not armed.
+    pub fn disarm(&mut self) -> Result<(), Error<DI::Error>> {
+        let saved = match self.wake {
+            Some(saved) => saved,
+            None => return Ok(()),
+        };
+
+        self.write_control_registers(saved.ctrl_regs)?;
+        self.set_register(registers::INT_CFG, saved.int_cfg)?;
+        self.iface
+            .write_registers(registers::INT_THS_L, &saved.int_ths)
+            .map_err(Error::Bus)?;
+        self.interrupt_source()?;
+        self.wake = None;
+        Ok(())
+    }
+
+    /// Set one of the LIS3MDL's register to a certain value.
+    /// Writes to reserved or read-only registers are refused with `Error::InvalidRegister`.
+    pub fn set_register(&mut self, reg: u8, value: u8) -> Result<(), Error<DI::Error>> {
+        if !registers::is_writable(reg) {
+            return Err(Error::InvalidRegister(reg));
+        }
+        self.iface.write_register(reg, value).map_err(Error::Bus)?;
+        self.shadow.update(reg, value);
+        Ok(())
     }
 
     /// Read one of the LIS3MDL's registers
-    pub fn read_register(&mut self, reg: u8) -> Result<u8, E> {
-        let mut resp = [0];
-        self.i2c.write_read(self.address, &[reg], &mut resp)?;
-        Ok(resp[0])
+    pub fn read_register(&mut self, reg: u8) -> Result<u8, Error<DI::Error>> {
+        self.iface.read_register(reg).map_err(Error::Bus)
     }
 
-    /// Reads the latest data, returning `Ok(None)` if any is not ready.
-    /// A `None` return does not necessarily indicate that anything has failed,
+    /// Reads the latest data, returning `Error::NotReady` if any is not ready.
+    /// A `NotReady` error does not necessarily indicate that anything has failed,
     /// and this function can be called immediately afterwards.
-    pub fn read(&mut self) -> Result<Option<(i16, i16, i16)>, E> {
-        if self.read_register(registers::STATUS_REG)? & 0b1000 != 0b1000 {
-            return Ok(None);
+    /// Returns `Error::InvalidConfiguration` while fast read is enabled; use `read_fast` then.
+    pub fn read(&mut self) -> Result<(i16, i16, i16), Error<DI::Error>> {
+        self.read_with_status().map(|sample| sample.data)
+    }
+
+    /// Like `read`, but also returns the status the sample was read with.
+    /// Its overrun flags (`xor`, `yor`, `zor`, `zyxor`) are set when samples were
+    /// dropped because they were not read before the next one was converted.
+    pub fn read_with_status(&mut self) -> Result<Sample, Error<DI::Error>> {
+        if self.shadow.fast_read() {
+            return Err(Error::InvalidConfiguration);
+        }
+        let status = self.status()?;
+        if !status.zyxda() {
+            return Err(Error::NotReady);
+        }
+        Ok(Sample {
+            status,
+            data: self.incremental_read_measurements(registers::OUT_X_L)?,
+        })
+    }
+
+    /// Reads the STATUS_REG register, holding the per axis data available and overrun flags.
+    pub fn status(&mut self) -> Result<StatusReg, Error<DI::Error>> {
+        self.read_register(registers::STATUS_REG)
+            .map(StatusReg::from_bits)
+    }
+
+    /// Triggers a single conversion and waits for its result, polling every millisecond
+    /// for at most `timeout` before giving up with `Error::Timeout`.
+    /// Afterwards the device is back in power-down, as it is after any single conversion.
+    /// Returns `Error::InvalidConfiguration` if a FAST_ODR data rate is configured, as the
+    /// device only supports single conversions at 0.625 to 80 Hz.
+    pub fn measure_once<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        timeout: Duration,
+    ) -> Result<(i16, i16, i16), Error<DI::Error>> {
+        // Single conversions only run at the DO rates, not the FAST_ODR ones
+        if self.config().data_rate.required_xy_mode().is_some() {
+            return Err(Error::InvalidConfiguration);
+        }
+
+        // Discard any sample left over from before, so it is not mistaken for the new one
+        match self.read() {
+            Ok(_) | Err(Error::NotReady) => {}
+            Err(e) => return Err(e),
         }
+
+        self.set_operating_mode(OperatingMode::SingleConversion)?;
+
+        let sample = self.poll_read(delay, timeout)?;
+
+        let mut reg = CtrlReg3::from_bits(self.shadow.get(registers::CTRL_REG3));
+        reg.set_operating_mode(OperatingMode::PowerDown);
+        self.shadow.update(registers::CTRL_REG3, reg.bits());
+
+        Ok(sample)
+    }
+
+    /// Runs the datasheet self test: at ±12 gauss and 80 Hz, averages five samples with
+    /// the self test coil off and five with it on, and checks the change on each axis
+    /// against the datasheet limits. The configuration of before is restored afterwards.
+    /// Keep strong magnets away from the sensor while it runs, as it takes about a quarter second.
+    pub fn self_test<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<SelfTestReport, Error<DI::Error>> {
+        // Restore the registers as they were, including bits `Config` does not cover
+        let saved = self.shadow.registers();
+        let report = self.run_self_test(delay, self.config());
+        let restored = self.write_control_registers(saved);
+        let report = report?;
+        restored?;
+        Ok(report)
+    }
+
+    fn run_self_test<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        saved: Config,
+    ) -> Result<SelfTestReport, Error<DI::Error>> {
+        self.apply(&self_test::config(saved))?;
+        delay.delay_us(self_test::SETTLE_US);
+        let off = self.sum_self_test_samples(delay)?;
+
+        let mut reg = CtrlReg1::from_bits(self.shadow.get(registers::CTRL_REG1));
+        reg.set_self_test(true);
+        self.set_register(registers::CTRL_REG1, reg.bits())?;
+        delay.delay_us(self_test::SELF_TEST_SETTLE_US);
+        let on = self.sum_self_test_samples(delay)?;
+
+        Ok(self_test::report(off, on))
+    }
+
+    /// Discards one sample, then sums the next `self_test::SAMPLES`.
+    fn sum_self_test_samples<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<(i32, i32, i32), Error<DI::Error>> {
+        self.poll_read(delay, self_test::SAMPLE_TIMEOUT)?;
+        let mut sum = (0, 0, 0);
+        for _ in 0..self_test::SAMPLES {
+            let sample = self.poll_read(delay, self_test::SAMPLE_TIMEOUT)?;
+            self_test::accumulate(&mut sum, sample);
+        }
+        Ok(sum)
+    }
+
+    /// Waits for the next sample, polling every millisecond for at most `timeout`.
+    fn poll_read<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        timeout: Duration,
+    ) -> Result<(i16, i16, i16), Error<DI::Error>> {
+        let mut waited = Duration::from_millis(0);
+        loop {
+            match self.read() {
+                Err(Error::NotReady) if waited < timeout => {
+                    delay.delay_us(SINGLE_CONVERSION_POLL_US);
+                    waited += Duration::from_micros(SINGLE_CONVERSION_POLL_US as u64);
+                }
+                Err(Error::NotReady) => return Err(Error::Timeout),
+                result => return result,
+            }
+        }
+    }
+
+    /// Reads the high byte of the latest data on each axis in a single 3 byte transaction,
+    /// returning `Error::NotReady` if any is not ready.
+    /// Fast read must have been enabled with `set_fast_read`, otherwise this returns
+    /// `Error::InvalidConfiguration`.
+    pub fn read_fast(&mut self) -> Result<(i8, i8, i8), Error<DI::Error>> {
+        if !self.shadow.fast_read() {
+            return Err(Error::InvalidConfiguration);
+        }
+        if !StatusReg::from_bits(self.read_register(registers::STATUS_REG)?).zyxda() {
+            return Err(Error::NotReady);
+        }
+
+        let mut values = [0; 3];
+        self.iface
+            .read_registers(registers::OUT_X_H, &mut values)
+            .map_err(Error::Bus)?;
+        Ok((values[0] as i8, values[1] as i8, values[2] as i8))
+    }
+
+    /// Reads the latest data in gauss, scaled by the full scale the driver last configured.
+    /// Like `read`, returns `Error::NotReady` if no new data is available.
+    pub fn read_gauss(&mut self) -> Result<MagneticField, Error<DI::Error>> {
+        let raw = self.read()?;
+        Ok(to_field(raw, self.config().full_scale, 1.0))
+    }
+
+    /// Reads the latest data in microtesla. See `read_gauss`.
+    pub fn read_microtesla(&mut self) -> Result<MagneticField, Error<DI::Error>> {
+        let raw = self.read()?;
+        Ok(to_field(raw, self.config().full_scale, 100.0))
+    }
+
+    /// Reads the latest data in tesla. See `read_gauss`.
+    pub fn read_tesla(&mut self) -> Result<MagneticField, Error<DI::Error>> {
+        let raw = self.read()?;
+        Ok(to_field(raw, self.config().full_scale, 1e-4))
+    }
+
+    /// Reads the latest data in milligauss using only integer math. See `read_gauss`.
+    pub fn read_milligauss(&mut self) -> Result<MagneticField<i32>, Error<DI::Error>> {
+        let raw = self.read()?;
+        Ok(to_milligauss(raw, self.config().full_scale))
+    }
+
+    /// Waits for the DRDY pin to signal a new sample and reads it, without polling
+    /// STATUS_REG over the bus. The pin is checked every 100 µs, sleeping on `delay`
+    /// in between, for at most `timeout` before giving up with `Error::Timeout`;
+    /// the async driver sleeps until the pin goes high instead.
+    pub fn wait_for_data<P: InputPin, D: DelayNs>(
+        &mut self,
+        drdy: &mut P,
+        delay: &mut D,
+        timeout: Duration,
+    ) -> Result<(i16, i16, i16), Error<DI::Error>> {
+        if self.shadow.fast_read() {
+            return Err(Error::InvalidConfiguration);
+        }
+        wait_for_pin(drdy, true, delay, timeout)?;
         self.incremental_read_measurements(registers::OUT_X_L)
-            .map(|o| Some(o))
     }
 
-    /// This method of extracting measurements only works if the 2nd bit (0-indexed) of the CTRL_3C register is set to 1.
-    fn incremental_read_measurements(&mut self, start_reg: u8) -> Result<(i16, i16, i16), E> {
+    /// Waits for the INT pin to signal a threshold crossing and reads INT_SRC,
+    /// which also clears a latched interrupt. The pin's polarity is taken from INT_CFG.
+    /// Like `wait_for_data`, gives up with `Error::Timeout` after `timeout`.
+    pub fn wait_for_threshold<P: InputPin, D: DelayNs>(
+        &mut self,
+        int: &mut P,
+        delay: &mut D,
+        timeout: Duration,
+    ) -> Result<IntSrc, Error<DI::Error>> {
+        let reg = IntCfg::from_bits(self.read_register(registers::INT_CFG)?);
+        wait_for_pin(int, reg.active_high(), delay, timeout)?;
+        self.interrupt_source()
+    }
+
+    /// Reads the on-die temperature sensor in degrees Celsius.
+    /// The sensor must be enabled with `set_temperature_enabled` first.
+    pub fn read_temperature(&mut self) -> Result<f32, Error<DI::Error>> {
+        let mut values = [0; 2];
+        self.iface
+            .read_registers(registers::TEMP_OUT_L, &mut values)
+            .map_err(Error::Bus)?;
+        Ok(to_celsius(i16::from_le_bytes(values)))
+    }
+
+    /// Reads the six output registers starting at `start_reg` in a single auto-incremented burst.
+    fn incremental_read_measurements(
+        &mut self,
+        start_reg: u8,
+    ) -> Result<(i16, i16, i16), Error<DI::Error>> {
         let mut values = [0; 6];
-        self.i2c
-            .write_read(self.address, &[start_reg], &mut values)?;
+        self.iface
+            .read_registers(start_reg, &mut values)
+            .map_err(Error::Bus)?;
 
-        Ok((
-            (values[1] as i16) << 8 | values[0] as i16,
-            (values[3] as i16) << 8 | values[2] as i16,
-            (values[5] as i16) << 8 | values[4] as i16,
-        ))
+        Ok(decode_measurements(&values))
+    }
+}
+
+/// Waits for `pin` to read `high`, checking it every `PIN_POLL_US` for at most `timeout`.
+fn wait_for_pin<P: InputPin, D: DelayNs, E>(
+    pin: &mut P,
+    high: bool,
+    delay: &mut D,
+    timeout: Duration,
+) -> Result<(), Error<E>> {
+    let mut waited = Duration::from_millis(0);
+    while pin.is_high().map_err(|e| Error::Pin(e.kind()))? != high {
+        if waited >= timeout {
+            return Err(Error::Timeout);
+        }
+        delay.delay_us(PIN_POLL_US);
+        waited += Duration::from_micros(PIN_POLL_US as u64);
     }
+    Ok(())
+}
+
+fn decode_measurements(values: &[u8; 6]) -> (i16, i16, i16) {
+    (
+        (values[1] as i16) << 8 | values[0] as i16,
+        (values[3] as i16) << 8 | values[2] as i16,
+        (values[5] as i16) << 8 | values[4] as i16,
+    )
 }
 
-fn test_lism3mdl_addr<I: WriteRead>(i2c: &mut I, address: u8) -> Result<bool, I::Error> {
-    let mut resp = [LIS3MDL_WHO_ID + 1];
+fn read_who_am_i<I: I2c>(i2c: &mut I, address: u8) -> Result<u8, I::Error> {
+    let mut resp = [0];
     i2c.write_read(address, &[registers::WHO_AM_I], &mut resp)?;
-    Ok(resp[0] == LIS3MDL_WHO_ID)
+    Ok(resp[0])
 }
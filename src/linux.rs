@@ -0,0 +1,39 @@
+//! Linux userspace backends, using `/dev/i2c-*` and `/dev/spidev*` through
+//! [linux-embedded-hal](https://crates.io/crates/linux-embedded-hal).
+
+extern crate std;
+
+use std::path::Path;
+
+use linux_embedded_hal::spidev::{SpiModeFlags, SpidevOptions};
+use linux_embedded_hal::{I2CError, I2cdev, SPIError, SpidevDevice};
+
+use crate::{Error, I2cInterface, SpiInterface, LIS3MDL};
+
+/// The LIS3MDL supports SPI clocks up to 10 MHz; stay well below that for long wires.
+const SPI_SPEED_HZ: u32 = 1_000_000;
+
+impl LIS3MDL<I2cInterface<I2cdev>> {
+    /// Opens an I2C bus such as `/dev/i2c-1` and autodetects the LIS3MDL on it.
+    pub fn open_i2c<P: AsRef<Path>>(path: P) -> Result<Self, Error<I2CError>> {
+        let i2c = I2cdev::new(path).map_err(|e| Error::Bus(e.into()))?;
+        Self::new(i2c)
+    }
+}
+
+impl LIS3MDL<SpiInterface<SpidevDevice>> {
+    /// Opens an SPI device such as `/dev/spidev0.0`, configures it for the LIS3MDL
+    /// (mode 3, 1 MHz) and checks that a LIS3MDL is on the other end.
+    pub fn open_spi<P: AsRef<Path>>(path: P) -> Result<Self, Error<SPIError>> {
+        let mut spi = SpidevDevice::open(path).map_err(Error::Bus)?;
+        let options = SpidevOptions::new()
+            .mode(SpiModeFlags::SPI_MODE_3)
+            .max_speed_hz(SPI_SPEED_HZ)
+            .build();
+        spi.0
+            .configure(&options)
+            .map_err(|e| Error::Bus(e.into()))?;
+
+        Self::new_spi(spi)
+    }
+}
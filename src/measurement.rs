@@ -0,0 +1,147 @@
+use crate::registers::StatusReg;
+use crate::FullScale;
+
+/// A raw sample together with the status it was read with,
+/// see [`LIS3MDL::read_with_status`](crate::LIS3MDL::read_with_status).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    pub status: StatusReg,
+    /// The raw x, y and z outputs, as returned by `read`.
+    pub data: (i16, i16, i16),
+}
+
+/// A magnetic field measurement on all three axes.
+///
+/// `MagneticField<f32>` is returned in gauss, microtesla or tesla depending on how it was read;
+/// `MagneticField<i32>` is returned in milligauss for targets without an FPU.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MagneticField<T = f32> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+impl FullScale {
+    /// The sensitivity in LSB per gauss at this full scale.
+    pub fn sensitivity(self) -> u16 {
+        match self {
+            FullScale::Four => 6842,
+            FullScale::Eight => 3421,
+            FullScale::Twelve => 2281,
+            FullScale::Sixteen => 1711,
+        }
+    }
+}
+
+/// Converts a raw sample to `units_per_gauss` units at the given full scale.
+pub(crate) fn to_field(
+    raw: (i16, i16, i16),
+    scale: FullScale,
+    units_per_gauss: f32,
+) -> MagneticField {
+    let factor = units_per_gauss / scale.sensitivity() as f32;
+    MagneticField {
+        x: raw.0 as f32 * factor,
+        y: raw.1 as f32 * factor,
+        z: raw.2 as f32 * factor,
+    }
+}
+
+/// Converts a raw sample to milligauss at the given full scale using only integer math.
+pub(crate) fn to_milligauss(raw: (i16, i16, i16), scale: FullScale) -> MagneticField<i32> {
+    let sensitivity = scale.sensitivity() as i32;
+    MagneticField {
+        x: raw.0 as i32 * 1000 / sensitivity,
+        y: raw.1 as i32 * 1000 / sensitivity,
+        z: raw.2 as i32 * 1000 / sensitivity,
+    }
+}
+
+/// Converts a raw TEMP_OUT sample to degrees Celsius.
+/// The sensor outputs 8 LSB/°C, with zero corresponding to 25 °C.
+pub(crate) fn to_celsius(raw: i16) -> f32 {
+    25.0 + raw as f32 / 8.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCALES: [FullScale; 4] = [
+        FullScale::Four,
+        FullScale::Eight,
+        FullScale::Twelve,
+        FullScale::Sixteen,
+    ];
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() <= expected.abs() * 1e-6,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn one_gauss_per_sensitivity_at_every_full_scale() {
+        for &scale in SCALES.iter() {
+            let lsb = scale.sensitivity() as i16;
+            let field = to_field((lsb, -lsb, 0), scale, 1.0);
+            assert_close(field.x, 1.0);
+            assert_close(field.y, -1.0);
+            assert_eq!(field.z, 0.0);
+
+            let field = to_field((lsb, -lsb, 0), scale, 100.0);
+            assert_close(field.x, 100.0);
+            assert_close(field.y, -100.0);
+        }
+    }
+
+    #[test]
+    fn field_covers_the_full_range() {
+        let field = to_field((i16::MAX, i16::MIN, 0), FullScale::Four, 1.0);
+        assert_close(field.x, 32767.0 / 6842.0);
+        assert_close(field.y, -32768.0 / 6842.0);
+    }
+
+    #[test]
+    fn milligauss_at_every_full_scale() {
+        for &scale in SCALES.iter() {
+            let lsb = scale.sensitivity() as i16;
+            assert_eq!(
+                to_milligauss((lsb, -lsb, lsb / 2), scale),
+                MagneticField {
+                    x: 1000,
+                    y: -1000,
+                    z: lsb as i32 / 2 * 1000 / lsb as i32,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn milligauss_truncates_towards_zero_without_overflow() {
+        assert_eq!(
+            to_milligauss((1, -1, 0), FullScale::Four),
+            MagneticField { x: 0, y: 0, z: 0 }
+        );
+        assert_eq!(
+            to_milligauss((i16::MAX, i16::MIN, -1711), FullScale::Sixteen),
+            MagneticField {
+                x: 19150,
+                y: -19151,
+                z: -1000,
+            }
+        );
+    }
+
+    #[test]
+    fn celsius_is_offset_from_25() {
+        assert_eq!(to_celsius(0), 25.0);
+        assert_eq!(to_celsius(8), 26.0);
+        assert_eq!(to_celsius(-8), 24.0);
+        assert_eq!(to_celsius(4), 25.5);
+        assert_eq!(to_celsius(-200), 0.0);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{AxisMode, Config, OperatingMode, OutputDataRate};
+
+/// Typical supply current in power-down mode, in µA.
+const POWER_DOWN_CURRENT_UA: f32 = 1.0;
+
+/// The data rate the per mode supply currents are taken to apply at. This is an assumption
+/// of the estimate, not a test condition stated alongside the figures in the datasheet.
+const REFERENCE_RATE_MILLIHZ: f32 = 80_000.0;
+
+impl AxisMode {
+    /// The typical supply current in µA the datasheet lists for continuous conversion
+    /// in this mode, which the estimate assumes to apply at 80 Hz.
+    fn typical_current_ua(self) -> f32 {
+        match self {
+            AxisMode::LowPower => 40.0,
+            AxisMode::MediumPerformance => 270.0,
+            AxisMode::HighPerformance => 495.0,
+            AxisMode::UltraPerformance => 830.0,
+        }
+    }
+
+    /// The estimated current above power-down at `rate`, scaling the typical current
+    /// linearly with the number of conversions per second.
+    fn active_current_ua(self, rate: OutputDataRate) -> f32 {
+        (self.typical_current_ua() - POWER_DOWN_CURRENT_UA) * rate.millihertz() as f32
+            / REFERENCE_RATE_MILLIHZ
+    }
+}
+
+/// Estimates the supply current in µA with all three axes in `mode`.
+///
+/// This is a rough budget, not a measurement: it assumes the datasheet's typical currents
+/// apply at 80 Hz and that the charge drawn per conversion is constant, so that they scale
+/// linearly with the data rate, and ignores temperature, supply voltage and bus traffic.
+/// For `SingleConversion` it assumes a conversion is triggered once every `data_rate` period.
+/// A FAST_ODR `data_rate` is estimated in the axis mode it requires, whatever `mode` is,
+/// just as setting it switches the device to that mode.
+pub fn estimate_supply_current(
+    mode: AxisMode,
+    data_rate: OutputDataRate,
+    operating_mode: OperatingMode,
+) -> f32 {
+    match operating_mode {
+        OperatingMode::PowerDown => POWER_DOWN_CURRENT_UA,
+        OperatingMode::ContinuousConversion | OperatingMode::SingleConversion => {
+            let mode = data_rate.required_xy_mode().unwrap_or(mode);
+            POWER_DOWN_CURRENT_UA + mode.active_current_ua(data_rate)
+        }
+    }
+}
+
+impl Config {
+    /// Estimates the supply current in µA of this configuration,
+    /// with the same caveats as [`estimate_supply_current`](crate::estimate_supply_current).
+    /// The x and y axes are counted as two thirds of the conversion current and z as one third,
+    /// and low-power mode as the low-power axis mode at 0.625 Hz.
+    pub fn estimated_supply_current(&self) -> f32 {
+        if self.operating_mode == OperatingMode::PowerDown {
+            return POWER_DOWN_CURRENT_UA;
+        }
+        let (xy_mode, z_mode, rate) = if self.low_power {
+            (
+                AxisMode::LowPower,
+                AxisMode::LowPower,
+                OutputDataRate::MilliHz625,
+            )
+        } else {
+            let xy_mode = self.data_rate.required_xy_mode().unwrap_or(self.xy_mode);
+            (xy_mode, self.z_mode, self.data_rate)
+        };
+        POWER_DOWN_CURRENT_UA
+            + xy_mode.active_current_ua(rate) * 2.0 / 3.0
+            + z_mode.active_current_ua(rate) / 3.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_scales_with_data_rate() {
+        let at_80 = estimate_supply_current(
+            AxisMode::HighPerformance,
+            OutputDataRate::Hz80,
+            OperatingMode::ContinuousConversion,
+        );
+        let at_40 = estimate_supply_current(
+            AxisMode::HighPerformance,
+            OutputDataRate::Hz40,
+            OperatingMode::ContinuousConversion,
+        );
+        assert_eq!(at_80, 495.0);
+        assert_eq!(at_40, 248.0);
+        assert_eq!(
+            estimate_supply_current(
+                AxisMode::HighPerformance,
+                OutputDataRate::Hz80,
+                OperatingMode::PowerDown
+            ),
+            POWER_DOWN_CURRENT_UA
+        );
+    }
+
+    #[test]
+    fn fast_data_rate_is_estimated_in_its_required_mode() {
+        let mismatched = estimate_supply_current(
+            AxisMode::UltraPerformance,
+            OutputDataRate::Hz1000,
+            OperatingMode::ContinuousConversion,
+        );
+        let matched = estimate_supply_current(
+            AxisMode::LowPower,
+            OutputDataRate::Hz1000,
+            OperatingMode::ContinuousConversion,
+        );
+        assert_eq!(mismatched, matched);
+    }
+}
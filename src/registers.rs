@@ -1,3 +1,13 @@
+//! Register addresses of the LIS3MDL, and typed views of its configuration and status registers.
+
+use crate::{AxisMode, FullScale, OperatingMode, OutputDataRate};
+
+pub const OFFSET_X_REG_L: u8 = 0x05;
+pub const OFFSET_X_REG_H: u8 = 0x06;
+pub const OFFSET_Y_REG_L: u8 = 0x07;
+pub const OFFSET_Y_REG_H: u8 = 0x08;
+pub const OFFSET_Z_REG_L: u8 = 0x09;
+pub const OFFSET_Z_REG_H: u8 = 0x0A;
 pub const WHO_AM_I: u8 = 0x0F;
 pub const CTRL_REG1: u8 = 0x20;
 pub const CTRL_REG2: u8 = 0x21;
@@ -17,3 +27,594 @@ pub const INT_CFG: u8 = 0x30;
 pub const INT_SRC: u8 = 0x31;
 pub const INT_THS_L: u8 = 0x32;
 pub const INT_THS_H: u8 = 0x33;
+
+/// How a register may be accessed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    ReadOnly,
+    ReadWrite,
+}
+
+/// Looks up how `reg` may be accessed, returning `None` for reserved addresses
+/// (0x00-0x04, 0x0B-0x0E, 0x10-0x1F, 0x25-0x26 and everything past 0x33),
+/// which must never be written as they hold factory calibration.
+pub fn access(reg: u8) -> Option<Access> {
+    match reg {
+        OFFSET_X_REG_L..=OFFSET_Z_REG_H => Some(Access::ReadWrite),
+        WHO_AM_I => Some(Access::ReadOnly),
+        CTRL_REG1..=CTRL_REG5 => Some(Access::ReadWrite),
+        STATUS_REG..=TEMP_OUT_H => Some(Access::ReadOnly),
+        INT_CFG => Some(Access::ReadWrite),
+        INT_SRC => Some(Access::ReadOnly),
+        INT_THS_L..=INT_THS_H => Some(Access::ReadWrite),
+        _ => None,
+    }
+}
+
+/// Whether `reg` is a register that may safely be written.
+pub fn is_writable(reg: u8) -> bool {
+    access(reg) == Some(Access::ReadWrite)
+}
+
+macro_rules! register {
+    ($(#[$meta:meta])* $name:ident = $reset:expr) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct $name(u8);
+
+        impl $name {
+            /// Decodes the raw register value.
+            pub const fn from_bits(bits: u8) -> Self {
+                $name(bits)
+            }
+
+            /// Encodes the register into the raw value to write to the device.
+            pub const fn bits(self) -> u8 {
+                self.0
+            }
+
+            #[allow(dead_code)]
+            fn bit(self, n: u8) -> bool {
+                self.0 & (1 << n) != 0
+            }
+
+            #[allow(dead_code)]
+            fn set_bit(&mut self, n: u8, value: bool) {
+                self.0 = (self.0 & !(1 << n)) | ((value as u8) << n);
+            }
+
+            #[allow(dead_code)]
+            fn set_field(&mut self, shift: u8, mask: u8, value: u8) {
+                self.0 = (self.0 & !(mask << shift)) | ((value & mask) << shift);
+            }
+        }
+
+        /// The value the register holds after power-on or reboot.
+        impl Default for $name {
+            fn default() -> Self {
+                $name($reset)
+            }
+        }
+
+        impl From<u8> for $name {
+            fn from(bits: u8) -> Self {
+                $name(bits)
+            }
+        }
+
+        impl From<$name> for u8 {
+            fn from(reg: $name) -> u8 {
+                reg.0
+            }
+        }
+    };
+}
+
+register! {
+    /// CTRL_REG1: temperature sensor, x/y operative mode, output data rate and self test.
+    CtrlReg1 = 0b0001_0000
+}
+
+impl CtrlReg1 {
+    pub fn temperature_enabled(self) -> bool {
+        self.bit(7)
+    }
+
+    pub fn set_temperature_enabled(&mut self, enabled: bool) {
+        self.set_bit(7, enabled)
+    }
+
+    pub fn xy_mode(self) -> AxisMode {
+        AxisMode::from_bitcode(self.0 >> 5)
+    }
+
+    pub fn set_xy_mode(&mut self, mode: AxisMode) {
+        self.set_field(5, 0b11, mode.to_bitcode())
+    }
+
+    /// The output data rate, covering both the DO bits and FAST_ODR.
+    pub fn data_rate(self) -> OutputDataRate {
+        OutputDataRate::from_bitcode(self.0 >> 1, self.xy_mode())
+    }
+
+    /// Sets the output data rate. The FAST_ODR rates also set the x and y axis mode they require.
+    pub fn set_data_rate(&mut self, odr: OutputDataRate) {
+        if let Some(mode) = odr.required_xy_mode() {
+            self.set_xy_mode(mode);
+        }
+        self.set_field(1, 0b1111, odr.to_bitcode())
+    }
+
+    pub fn self_test(self) -> bool {
+        self.bit(0)
+    }
+
+    pub fn set_self_test(&mut self, enabled: bool) {
+        self.set_bit(0, enabled)
+    }
+}
+
+register! {
+    /// CTRL_REG2: full scale, reboot and soft reset.
+    CtrlReg2 = 0b0000_0000
+}
+
+impl CtrlReg2 {
+    pub fn full_scale(self) -> FullScale {
+        FullScale::from_bitcode(self.0 >> 5)
+    }
+
+    pub fn set_full_scale(&mut self, scale: FullScale) {
+        self.set_field(5, 0b11, scale.to_bitcode())
+    }
+
+    /// Reloads the trimming parameters from memory.
+    pub fn reboot(self) -> bool {
+        self.bit(3)
+    }
+
+    pub fn set_reboot(&mut self, reboot: bool) {
+        self.set_bit(3, reboot)
+    }
+
+    /// Resets the configuration and user registers to their defaults.
+    pub fn soft_reset(self) -> bool {
+        self.bit(2)
+    }
+
+    pub fn set_soft_reset(&mut self, reset: bool) {
+        self.set_bit(2, reset)
+    }
+}
+
+register! {
+    /// CTRL_REG3: low-power mode, SPI mode and system operating mode.
+    CtrlReg3 = 0b0000_0011
+}
+
+impl CtrlReg3 {
+    /// When set, the output data rate is forced to 0.625 Hz and no averaging is done.
+    pub fn low_power(self) -> bool {
+        self.bit(5)
+    }
+
+    pub fn set_low_power(&mut self, enabled: bool) {
+        self.set_bit(5, enabled)
+    }
+
+    /// When set, SPI is 3-wire instead of 4-wire.
+    pub fn spi_3_wire(self) -> bool {
+        self.bit(2)
+    }
+
+    pub fn set_spi_3_wire(&mut self, enabled: bool) {
+        self.set_bit(2, enabled)
+    }
+
+    pub fn operating_mode(self) -> OperatingMode {
+        OperatingMode::from_bitcode(self.0)
+    }
+
+    pub fn set_operating_mode(&mut self, mode: OperatingMode) {
+        self.set_field(0, 0b11, mode.to_bitcode())
+    }
+}
+
+register! {
+    /// CTRL_REG4: z operative mode and output endianness.
+    CtrlReg4 = 0b0000_0000
+}
+
+impl CtrlReg4 {
+    pub fn z_mode(self) -> AxisMode {
+        AxisMode::from_bitcode(self.0 >> 2)
+    }
+
+    pub fn set_z_mode(&mut self, mode: AxisMode) {
+        self.set_field(2, 0b11, mode.to_bitcode())
+    }
+
+    /// When set, the output registers are big endian.
+    pub fn big_endian(self) -> bool {
+        self.bit(1)
+    }
+
+    pub fn set_big_endian(&mut self, enabled: bool) {
+        self.set_bit(1, enabled)
+    }
+}
+
+register! {
+    /// CTRL_REG5: fast read and block data update.
+    CtrlReg5 = 0b0000_0000
+}
+
+impl CtrlReg5 {
+    /// When set, only the high byte of each output is read in a burst.
+    pub fn fast_read(self) -> bool {
+        self.bit(7)
+    }
+
+    pub fn set_fast_read(&mut self, enabled: bool) {
+        self.set_bit(7, enabled)
+    }
+
+    /// When set, the output registers are not updated until both bytes have been read.
+    pub fn block_data_update(self) -> bool {
+        self.bit(6)
+    }
+
+    pub fn set_block_data_update(&mut self, enabled: bool) {
+        self.set_bit(6, enabled)
+    }
+}
+
+register! {
+    /// STATUS_REG: data available and overrun flags.
+    StatusReg = 0b0000_0000
+}
+
+impl StatusReg {
+    /// New data on some axis overwrote data that had not been read.
+    pub fn zyxor(self) -> bool {
+        self.bit(7)
+    }
+
+    pub fn zor(self) -> bool {
+        self.bit(6)
+    }
+
+    pub fn yor(self) -> bool {
+        self.bit(5)
+    }
+
+    pub fn xor(self) -> bool {
+        self.bit(4)
+    }
+
+    /// New data is available on all axes.
+    pub fn zyxda(self) -> bool {
+        self.bit(3)
+    }
+
+    pub fn zda(self) -> bool {
+        self.bit(2)
+    }
+
+    pub fn yda(self) -> bool {
+        self.bit(1)
+    }
+
+    pub fn xda(self) -> bool {
+        self.bit(0)
+    }
+}
+
+register! {
+    /// INT_CFG: threshold interrupt configuration.
+    IntCfg = 0b1110_1000
+}
+
+impl IntCfg {
+    pub fn x_enabled(self) -> bool {
+        self.bit(7)
+    }
+
+    pub fn set_x_enabled(&mut self, enabled: bool) {
+        self.set_bit(7, enabled)
+    }
+
+    pub fn y_enabled(self) -> bool {
+        self.bit(6)
+    }
+
+    pub fn set_y_enabled(&mut self, enabled: bool) {
+        self.set_bit(6, enabled)
+    }
+
+    pub fn z_enabled(self) -> bool {
+        self.bit(5)
+    }
+
+    pub fn set_z_enabled(&mut self, enabled: bool) {
+        self.set_bit(5, enabled)
+    }
+
+    /// When set, the INT pin is active high.
+    pub fn active_high(self) -> bool {
+        self.bit(2)
+    }
+
+    pub fn set_active_high(&mut self, enabled: bool) {
+        self.set_bit(2, enabled)
+    }
+
+    /// Whether the interrupt stays asserted until INT_SRC is read.
+    /// The LIR bit is active low: it is cleared to latch the interrupt, as it is after reset.
+    pub fn latched(self) -> bool {
+        !self.bit(1)
+    }
+
+    pub fn set_latched(&mut self, enabled: bool) {
+        self.set_bit(1, !enabled)
+    }
+
+    pub fn interrupt_enabled(self) -> bool {
+        self.bit(0)
+    }
+
+    pub fn set_interrupt_enabled(&mut self, enabled: bool) {
+        self.set_bit(0, enabled)
+    }
+}
+
+register! {
+    /// INT_SRC: threshold interrupt source. Reading it clears a latched interrupt.
+    IntSrc = 0b0000_0000
+}
+
+impl IntSrc {
+    pub fn positive_x(self) -> bool {
+        self.bit(7)
+    }
+
+    pub fn positive_y(self) -> bool {
+        self.bit(6)
+    }
+
+    pub fn positive_z(self) -> bool {
+        self.bit(5)
+    }
+
+    pub fn negative_x(self) -> bool {
+        self.bit(4)
+    }
+
+    pub fn negative_y(self) -> bool {
+        self.bit(3)
+    }
+
+    pub fn negative_z(self) -> bool {
+        self.bit(2)
+    }
+
+    /// The internal measurement range overflowed.
+    pub fn mroi(self) -> bool {
+        self.bit(1)
+    }
+
+    /// An interrupt event has occurred.
+    pub fn interrupt(self) -> bool {
+        self.bit(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn access_matches_register_map() {
+        let table = [
+            (0x00, None),
+            (0x04, None),
+            (OFFSET_X_REG_L, Some(Access::ReadWrite)),
+            (OFFSET_Z_REG_H, Some(Access::ReadWrite)),
+            (0x0B, None),
+            (0x0E, None),
+            (WHO_AM_I, Some(Access::ReadOnly)),
+            (0x10, None),
+            (0x1F, None),
+            (CTRL_REG1, Some(Access::ReadWrite)),
+            (CTRL_REG5, Some(Access::ReadWrite)),
+            (0x25, None),
+            (0x26, None),
+            (STATUS_REG, Some(Access::ReadOnly)),
+            (OUT_X_L, Some(Access::ReadOnly)),
+            (TEMP_OUT_H, Some(Access::ReadOnly)),
+            (INT_CFG, Some(Access::ReadWrite)),
+            (INT_SRC, Some(Access::ReadOnly)),
+            (INT_THS_L, Some(Access::ReadWrite)),
+            (INT_THS_H, Some(Access::ReadWrite)),
+            (0x34, None),
+            (0xFF, None),
+        ];
+        for &(reg, expected) in table.iter() {
+            assert_eq!(access(reg), expected, "register {:#04x}", reg);
+            assert_eq!(is_writable(reg), expected == Some(Access::ReadWrite));
+        }
+    }
+
+    #[test]
+    fn ctrl_reg1_round_trips() {
+        let mut reg = CtrlReg1::from_bits(0);
+        reg.set_temperature_enabled(true);
+        reg.set_xy_mode(AxisMode::HighPerformance);
+        reg.set_data_rate(OutputDataRate::Hz40);
+        reg.set_self_test(true);
+        assert_eq!(reg.bits(), 0b1101_1001);
+
+        let reg = CtrlReg1::from_bits(reg.bits());
+        assert!(reg.temperature_enabled());
+        assert_eq!(reg.xy_mode(), AxisMode::HighPerformance);
+        assert_eq!(reg.data_rate(), OutputDataRate::Hz40);
+        assert!(reg.self_test());
+    }
+
+    const FAST_RATES: [(OutputDataRate, AxisMode); 4] = [
+        (OutputDataRate::Hz155, AxisMode::UltraPerformance),
+        (OutputDataRate::Hz300, AxisMode::HighPerformance),
+        (OutputDataRate::Hz560, AxisMode::MediumPerformance),
+        (OutputDataRate::Hz1000, AxisMode::LowPower),
+    ];
+
+    #[test]
+    fn fast_data_rates_set_fast_odr_and_their_xy_mode() {
+        for &(odr, mode) in FAST_RATES.iter() {
+            assert_eq!(odr.required_xy_mode(), Some(mode));
+
+            let mut reg = CtrlReg1::from_bits(0);
+            reg.set_xy_mode(if mode == AxisMode::LowPower {
+                AxisMode::UltraPerformance
+            } else {
+                AxisMode::LowPower
+            });
+            reg.set_data_rate(odr);
+            assert_eq!(reg.xy_mode(), mode);
+            assert_eq!(reg.bits() & 0b1_1110, 0b10);
+            assert_eq!(reg.data_rate(), odr);
+        }
+    }
+
+    #[test]
+    fn fast_odr_decodes_by_xy_mode() {
+        for &(odr, mode) in FAST_RATES.iter() {
+            assert_eq!(OutputDataRate::from_bitcode(0b1, mode), odr);
+        }
+    }
+
+    #[test]
+    fn ctrl_reg2_round_trips() {
+        let mut reg = CtrlReg2::from_bits(0);
+        reg.set_full_scale(FullScale::Twelve);
+        reg.set_reboot(true);
+        reg.set_soft_reset(true);
+        assert_eq!(reg.bits(), 0b0100_1100);
+
+        let reg = CtrlReg2::from_bits(reg.bits());
+        assert_eq!(reg.full_scale(), FullScale::Twelve);
+        assert!(reg.reboot());
+        assert!(reg.soft_reset());
+    }
+
+    #[test]
+    fn ctrl_reg3_round_trips() {
+        let mut reg = CtrlReg3::from_bits(0);
+        reg.set_low_power(true);
+        reg.set_spi_3_wire(true);
+        reg.set_operating_mode(OperatingMode::SingleConversion);
+        assert_eq!(reg.bits(), 0b0010_0101);
+
+        let reg = CtrlReg3::from_bits(reg.bits());
+        assert!(reg.low_power());
+        assert!(reg.spi_3_wire());
+        assert_eq!(reg.operating_mode(), OperatingMode::SingleConversion);
+        assert_eq!(
+            CtrlReg3::default().operating_mode(),
+            OperatingMode::PowerDown
+        );
+    }
+
+    #[test]
+    fn ctrl_reg4_round_trips() {
+        let mut reg = CtrlReg4::from_bits(0);
+        reg.set_z_mode(AxisMode::UltraPerformance);
+        reg.set_big_endian(true);
+        assert_eq!(reg.bits(), 0b0000_1110);
+
+        let reg = CtrlReg4::from_bits(reg.bits());
+        assert_eq!(reg.z_mode(), AxisMode::UltraPerformance);
+        assert!(reg.big_endian());
+    }
+
+    #[test]
+    fn ctrl_reg5_round_trips() {
+        let mut reg = CtrlReg5::from_bits(0);
+        reg.set_fast_read(true);
+        reg.set_block_data_update(true);
+        assert_eq!(reg.bits(), 0b1100_0000);
+
+        let reg = CtrlReg5::from_bits(reg.bits());
+        assert!(reg.fast_read());
+        assert!(reg.block_data_update());
+    }
+
+    #[test]
+    fn status_reg_decodes_each_flag() {
+        let flags: [fn(StatusReg) -> bool; 8] = [
+            StatusReg::xda,
+            StatusReg::yda,
+            StatusReg::zda,
+            StatusReg::zyxda,
+            StatusReg::xor,
+            StatusReg::yor,
+            StatusReg::zor,
+            StatusReg::zyxor,
+        ];
+        for (bit, flag) in flags.iter().enumerate() {
+            let reg = StatusReg::from_bits(1 << bit);
+            assert!(flag(reg));
+            assert_eq!(flags.iter().filter(|f| f(reg)).count(), 1);
+        }
+    }
+
+    #[test]
+    fn int_cfg_round_trips_and_keeps_reserved_bit() {
+        let mut reg = IntCfg::default();
+        reg.set_x_enabled(true);
+        reg.set_y_enabled(false);
+        reg.set_z_enabled(true);
+        reg.set_active_high(true);
+        reg.set_latched(false);
+        reg.set_interrupt_enabled(true);
+        assert_eq!(reg.bits(), 0b1010_1111);
+
+        let reg = IntCfg::from_bits(reg.bits());
+        assert!(reg.x_enabled());
+        assert!(!reg.y_enabled());
+        assert!(reg.z_enabled());
+        assert!(reg.active_high());
+        assert!(!reg.latched());
+        assert!(reg.interrupt_enabled());
+    }
+
+    #[test]
+    fn int_cfg_latches_with_lir_cleared() {
+        assert!(IntCfg::default().latched());
+
+        let mut reg = IntCfg::default();
+        reg.set_latched(false);
+        assert_eq!(reg.bits() & 0b10, 0b10);
+        reg.set_latched(true);
+        assert_eq!(reg.bits(), IntCfg::default().bits());
+    }
+
+    #[test]
+    fn int_src_decodes_each_flag() {
+        let flags: [fn(IntSrc) -> bool; 8] = [
+            IntSrc::interrupt,
+            IntSrc::mroi,
+            IntSrc::negative_z,
+            IntSrc::negative_y,
+            IntSrc::negative_x,
+            IntSrc::positive_z,
+            IntSrc::positive_y,
+            IntSrc::positive_x,
+        ];
+        for (bit, flag) in flags.iter().enumerate() {
+            let reg = IntSrc::from_bits(1 << bit);
+            assert!(flag(reg));
+            assert_eq!(flags.iter().filter(|f| f(reg)).count(), 1);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use core::time::Duration;
+
+use crate::{AxisMode, Config, Error, FullScale, MagneticField, OperatingMode, OutputDataRate};
+
+/// The number of samples averaged with self test off and with it on.
+pub(crate) const SAMPLES: i32 = 5;
+
+/// How long to wait for the device to settle after changing its configuration, in microseconds.
+pub(crate) const SETTLE_US: u32 = 20_000;
+/// How long to wait after enabling the self test coil, in microseconds.
+pub(crate) const SELF_TEST_SETTLE_US: u32 = 60_000;
+/// How long to wait for a sample at 80 Hz before giving up.
+pub(crate) const SAMPLE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The range of the self test deltas on the x and y axes, in gauss.
+const XY_LIMITS: (f32, f32) = (1.0, 3.0);
+/// The range of the self test delta on the z axis, in gauss.
+const Z_LIMITS: (f32, f32) = (0.1, 1.0);
+
+/// The outcome of [`LIS3MDL::self_test`](crate::LIS3MDL::self_test).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfTestReport {
+    /// How much the field changed on each axis when the self test was enabled, in gauss.
+    pub delta: MagneticField,
+    /// Whether every delta lies within the datasheet limits.
+    pub passed: bool,
+}
+
+impl SelfTestReport {
+    fn new(delta: MagneticField) -> Self {
+        let within = |value: f32, (min, max): (f32, f32)| (min..=max).contains(&value.abs());
+        SelfTestReport {
+            delta,
+            passed: within(delta.x, XY_LIMITS)
+                && within(delta.y, XY_LIMITS)
+                && within(delta.z, Z_LIMITS),
+        }
+    }
+
+    /// Turns a failed verdict into `Error::SelfTestFailed`, for callers that only need the verdict.
+    pub fn into_result<E>(self) -> Result<Self, Error<E>> {
+        if self.passed {
+            Ok(self)
+        } else {
+            Err(Error::SelfTestFailed)
+        }
+    }
+}
+
+/// The configuration the self test runs in: ±12 gauss at 80 Hz, converting continuously,
+/// with fast read off so that full samples can be read.
+pub(crate) fn config(base: Config) -> Config {
+    Config {
+        temperature_enabled: false,
+        xy_mode: AxisMode::LowPower,
+        data_rate: OutputDataRate::Hz80,
+        self_test: false,
+        full_scale: FullScale::Twelve,
+        low_power: false,
+        operating_mode: OperatingMode::ContinuousConversion,
+        z_mode: AxisMode::LowPower,
+        fast_read: false,
+        ..base
+    }
+}
+
+/// Adds a sample to a running sum.
+pub(crate) fn accumulate(sum: &mut (i32, i32, i32), sample: (i16, i16, i16)) {
+    sum.0 += sample.0 as i32;
+    sum.1 += sample.1 as i32;
+    sum.2 += sample.2 as i32;
+}
+
+/// Builds the report from the sums of the samples taken with self test off and on.
+pub(crate) fn report(off: (i32, i32, i32), on: (i32, i32, i32)) -> SelfTestReport {
+    let factor = 1.0 / (SAMPLES as f32 * FullScale::Twelve.sensitivity() as f32);
+    SelfTestReport::new(MagneticField {
+        x: (on.0 - off.0) as f32 * factor,
+        y: (on.1 - off.1) as f32 * factor,
+        z: (on.2 - off.2) as f32 * factor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The sums of five samples of `gauss` on each axis at ±12 gauss.
+    fn sums(gauss: (f32, f32, f32)) -> (i32, i32, i32) {
+        let raw = |g: f32| (g * FullScale::Twelve.sensitivity() as f32) as i32 * SAMPLES;
+        (raw(gauss.0), raw(gauss.1), raw(gauss.2))
+    }
+
+    #[test]
+    fn deltas_within_limits_pass_in_either_direction() {
+        assert!(report((0, 0, 0), sums((1.1, 2.9, 0.2))).passed);
+        assert!(report((0, 0, 0), sums((-2.0, -1.1, -0.9))).passed);
+    }
+
+    #[test]
+    fn each_limit_fails_the_test() {
+        let off = (0, 0, 0);
+        assert!(!report(off, sums((0.9, 2.0, 0.5))).passed);
+        assert!(!report(off, sums((3.1, 2.0, 0.5))).passed);
+        assert!(!report(off, sums((2.0, 0.9, 0.5))).passed);
+        assert!(!report(off, sums((2.0, 3.1, 0.5))).passed);
+        assert!(!report(off, sums((2.0, 2.0, 0.05))).passed);
+        assert!(!report(off, sums((2.0, 2.0, 1.1))).passed);
+    }
+}
@@ -0,0 +1,111 @@
+#![cfg(feature = "async")]
+
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+
+use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use lis3mdl::registers::{CTRL_REG1, OUT_X_L, STATUS_REG, WHO_AM_I};
+use lis3mdl::{
+    AxisMode, Config, FullScale, LIS3MDLAsync, OperatingMode, OutputDataRate, SlaveAddr,
+};
+
+const ADDRESS: u8 = 0x1E;
+
+/// CTRL_REG1 through CTRL_REG5 after power-on.
+const RESET_CONFIG: [u8; 5] = [0x10, 0x00, 0x03, 0x00, 0x00];
+
+/// Runs a future that never has to wait, as none of the mocks do.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn new_falls_back_to_sa1_low_and_resyncs() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x1E, vec![WHO_AM_I], vec![0])
+            .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+        I2cTransaction::write_read(0x1C, vec![WHO_AM_I], vec![0x3D]),
+        I2cTransaction::write_read(
+            0x1C,
+            vec![CTRL_REG1 | 0x80],
+            vec![0x10, 0x40, 0x03, 0x00, 0x00],
+        ),
+    ]);
+
+    let lis3mdl = block_on(LIS3MDLAsync::new(i2c)).unwrap();
+    assert_eq!(lis3mdl.config().full_scale, FullScale::Twelve);
+
+    lis3mdl.destroy().done();
+}
+
+#[test]
+fn new_reports_foreign_device() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x1E, vec![WHO_AM_I], vec![0x48]),
+        I2cTransaction::write_read(0x1C, vec![WHO_AM_I], vec![0])
+            .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+    ]);
+
+    let mut bus = i2c.clone();
+    assert_eq!(
+        block_on(LIS3MDLAsync::new(i2c)).err(),
+        Some(lis3mdl::Error::WrongDeviceId(0x48))
+    );
+
+    bus.done();
+}
+
+#[test]
+fn read_auto_increments_and_decodes_axes() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(ADDRESS, vec![STATUS_REG], vec![0b1000]),
+        I2cTransaction::write_read(
+            ADDRESS,
+            vec![OUT_X_L | 0x80],
+            vec![0x34, 0x12, 0xFE, 0xFF, 0x00, 0x7F],
+        ),
+        I2cTransaction::write_read(ADDRESS, vec![STATUS_REG], vec![0]),
+    ]);
+
+    let mut lis3mdl = LIS3MDLAsync::new_with_address(i2c, SlaveAddr::Sa1High);
+    assert_eq!(block_on(lis3mdl.read()), Ok((0x1234, -2, 0x7F00)));
+    assert_eq!(block_on(lis3mdl.read()), Err(lis3mdl::Error::NotReady));
+
+    lis3mdl.destroy().done();
+}
+
+#[test]
+fn apply_writes_all_control_registers_in_one_burst() {
+    let config = Config {
+        temperature_enabled: true,
+        xy_mode: AxisMode::UltraPerformance,
+        data_rate: OutputDataRate::Hz10,
+        full_scale: FullScale::Eight,
+        operating_mode: OperatingMode::ContinuousConversion,
+        z_mode: AxisMode::UltraPerformance,
+        block_data_update: true,
+        ..Config::default()
+    };
+    let i2c = I2cMock::new(&[
+        I2cTransaction::transaction_start(ADDRESS),
+        I2cTransaction::write(ADDRESS, vec![CTRL_REG1 | 0x80]),
+        I2cTransaction::write(ADDRESS, vec![0xF0, 0x20, 0x00, 0x0C, 0x40]),
+        I2cTransaction::transaction_end(ADDRESS),
+        I2cTransaction::write_read(ADDRESS, vec![CTRL_REG1 | 0x80], RESET_CONFIG.to_vec()),
+    ]);
+
+    let mut lis3mdl = LIS3MDLAsync::new_with_address(i2c, SlaveAddr::Sa1High);
+    block_on(lis3mdl.apply(&config)).unwrap();
+    assert_eq!(lis3mdl.config(), config);
+    assert_eq!(block_on(lis3mdl.read_config()), Ok(Config::default()));
+
+    lis3mdl.destroy().done();
+}
@@ -0,0 +1,59 @@
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use lis3mdl::registers::{CTRL_REG1, CTRL_REG2, INT_CFG, INT_SRC, INT_THS_L};
+use lis3mdl::{Axes, FullScale, I2cInterface, SlaveAddr, LIS3MDL};
+
+const ADDRESS: u8 = 0x1E;
+
+fn burst_write(reg: u8, values: &[u8]) -> Vec<I2cTransaction> {
+    vec![
+        I2cTransaction::transaction_start(ADDRESS),
+        I2cTransaction::write(ADDRESS, vec![reg | 0x80]),
+        I2cTransaction::write(ADDRESS, values.to_vec()),
+        I2cTransaction::transaction_end(ADDRESS),
+    ]
+}
+
+#[test]
+fn state_survives_release_and_attach() {
+    let mut transactions = vec![
+        I2cTransaction::write(ADDRESS, vec![CTRL_REG2, 0x60]),
+        I2cTransaction::write_read(ADDRESS, vec![INT_CFG], vec![0xE8]),
+        I2cTransaction::write_read(ADDRESS, vec![INT_THS_L | 0x80], vec![0x00, 0x00]),
+    ];
+    // Arming at ±16 gauss, where 1 gauss is 1711 LSB
+    transactions.extend(burst_write(CTRL_REG1, &[0x00, 0x60, 0x20, 0x00, 0x00]));
+    transactions.extend(burst_write(INT_THS_L, &[0xAF, 0x06]));
+    transactions.push(I2cTransaction::write(ADDRESS, vec![INT_CFG, 0xE9]));
+    transactions.push(I2cTransaction::write_read(
+        ADDRESS,
+        vec![INT_SRC],
+        vec![0x00],
+    ));
+    // Disarming with the bus borrowed again, without re-reading anything
+    transactions.extend(burst_write(CTRL_REG1, &[0x10, 0x60, 0x03, 0x00, 0x00]));
+    transactions.push(I2cTransaction::write(ADDRESS, vec![INT_CFG, 0xE8]));
+    transactions.extend(burst_write(INT_THS_L, &[0x00, 0x00]));
+    transactions.push(I2cTransaction::write_read(
+        ADDRESS,
+        vec![INT_SRC],
+        vec![0x00],
+    ));
+    let mut bus = I2cMock::new(&transactions);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(&mut bus, SlaveAddr::Sa1High);
+    lis3mdl.set_full_scale(FullScale::Sixteen).unwrap();
+    lis3mdl.arm_wake_on_field(1.0, Axes::ALL).unwrap();
+    let (_, state) = lis3mdl.release();
+
+    let mut lis3mdl = LIS3MDL::attach(I2cInterface::new(&mut bus, ADDRESS), state);
+    assert_eq!(lis3mdl.config().full_scale, FullScale::Sixteen);
+    assert!(lis3mdl.config().low_power);
+    lis3mdl.disarm().unwrap();
+    let (_, state) = lis3mdl.release();
+
+    let lis3mdl = LIS3MDL::attach(I2cInterface::new(&mut bus, ADDRESS), state);
+    assert_eq!(lis3mdl.config().full_scale, FullScale::Sixteen);
+    assert!(!lis3mdl.config().low_power);
+
+    bus.done();
+}
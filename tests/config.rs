@@ -0,0 +1,80 @@
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use lis3mdl::registers::{CTRL_REG1, INT_SRC, OUT_X_L, STATUS_REG};
+use lis3mdl::{
+    AxisMode, Config, Error, FullScale, OperatingMode, OutputDataRate, SlaveAddr, LIS3MDL,
+};
+
+const ADDRESS: u8 = 0x1E;
+
+#[test]
+fn apply_writes_all_control_registers_in_one_burst() {
+    let config = Config {
+        temperature_enabled: true,
+        xy_mode: AxisMode::UltraPerformance,
+        data_rate: OutputDataRate::Hz10,
+        full_scale: FullScale::Eight,
+        operating_mode: OperatingMode::ContinuousConversion,
+        z_mode: AxisMode::UltraPerformance,
+        block_data_update: true,
+        ..Config::default()
+    };
+    let i2c = I2cMock::new(&[
+        I2cTransaction::transaction_start(ADDRESS),
+        I2cTransaction::write(ADDRESS, vec![CTRL_REG1 | 0x80]),
+        I2cTransaction::write(ADDRESS, vec![0xF0, 0x20, 0x00, 0x0C, 0x40]),
+        I2cTransaction::transaction_end(ADDRESS),
+    ]);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    lis3mdl.apply(&config).unwrap();
+    assert_eq!(lis3mdl.config(), config);
+
+    lis3mdl.destroy().done();
+}
+
+#[test]
+fn partial_setter_after_resync_is_a_single_write() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(
+            ADDRESS,
+            vec![CTRL_REG1 | 0x80],
+            vec![0xF1, 0x20, 0x00, 0x0C, 0x40],
+        ),
+        I2cTransaction::write(ADDRESS, vec![CTRL_REG1, 0xF9]),
+    ]);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    lis3mdl.resync().unwrap();
+    lis3mdl.set_data_rate(OutputDataRate::Hz40).unwrap();
+    assert_eq!(lis3mdl.config().full_scale, FullScale::Eight);
+
+    lis3mdl.destroy().done();
+}
+
+#[test]
+fn set_register_refuses_reserved_and_read_only_registers() {
+    let i2c = I2cMock::new(&[]);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    for &reg in [0x00, 0x25, STATUS_REG, OUT_X_L, INT_SRC].iter() {
+        assert_eq!(
+            lis3mdl.set_register(reg, 0xFF),
+            Err(Error::InvalidRegister(reg))
+        );
+    }
+
+    lis3mdl.destroy().done();
+}
+
+#[test]
+fn set_xy_mode_and_data_rate_rejects_mismatched_fast_rate() {
+    let i2c = I2cMock::new(&[]);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    assert_eq!(
+        lis3mdl.set_xy_mode_and_data_rate(AxisMode::LowPower, OutputDataRate::Hz155),
+        Err(Error::InvalidConfiguration)
+    );
+
+    lis3mdl.destroy().done();
+}
@@ -0,0 +1,74 @@
+use core::time::Duration;
+
+use embedded_hal_mock::eh1::delay::NoopDelay;
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use lis3mdl::registers::{CTRL_REG1, CTRL_REG3, OUT_X_L, STATUS_REG};
+use lis3mdl::{Error, OperatingMode, OutputDataRate, SlaveAddr, LIS3MDL};
+
+const ADDRESS: u8 = 0x1E;
+
+/// OUT_X_L through OUT_Z_H for x = 0x1234, y = -2 and z = 0x7F00.
+const OUTPUT: [u8; 6] = [0x34, 0x12, 0xFE, 0xFF, 0x00, 0x7F];
+
+fn status(zyxda: bool) -> I2cTransaction {
+    I2cTransaction::write_read(ADDRESS, vec![STATUS_REG], vec![(zyxda as u8) << 3])
+}
+
+fn output(values: [u8; 6]) -> I2cTransaction {
+    I2cTransaction::write_read(ADDRESS, vec![OUT_X_L | 0x80], values.to_vec())
+}
+
+#[test]
+fn measure_once_discards_stale_sample_and_returns_to_power_down() {
+    let i2c = I2cMock::new(&[
+        status(true),
+        output([0x7F; 6]),
+        I2cTransaction::write(ADDRESS, vec![CTRL_REG3, 0x01]),
+        status(false),
+        status(false),
+        status(true),
+        output(OUTPUT),
+    ]);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    let sample = lis3mdl
+        .measure_once(&mut NoopDelay::new(), Duration::from_millis(20))
+        .unwrap();
+    assert_eq!(sample, (0x1234, -2, 0x7F00));
+    assert_eq!(lis3mdl.config().operating_mode, OperatingMode::PowerDown);
+
+    lis3mdl.destroy().done();
+}
+
+#[test]
+fn measure_once_times_out() {
+    let mut transactions = vec![
+        status(false),
+        I2cTransaction::write(ADDRESS, vec![CTRL_REG3, 0x01]),
+    ];
+    // One poll right away, then one every millisecond up to and including the timeout
+    transactions.extend((0..=3).map(|_| status(false)));
+    let i2c = I2cMock::new(&transactions);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    assert_eq!(
+        lis3mdl.measure_once(&mut NoopDelay::new(), Duration::from_millis(3)),
+        Err(Error::Timeout)
+    );
+
+    lis3mdl.destroy().done();
+}
+
+#[test]
+fn measure_once_refuses_fast_data_rates() {
+    let i2c = I2cMock::new(&[I2cTransaction::write(ADDRESS, vec![CTRL_REG1, 0x02])]);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    lis3mdl.set_data_rate(OutputDataRate::Hz1000).unwrap();
+    assert_eq!(
+        lis3mdl.measure_once(&mut NoopDelay::new(), Duration::from_millis(20)),
+        Err(Error::InvalidConfiguration)
+    );
+
+    lis3mdl.destroy().done();
+}
@@ -0,0 +1,70 @@
+use core::time::Duration;
+
+use embedded_hal_mock::eh1::delay::NoopDelay;
+use embedded_hal_mock::eh1::digital::{Mock as PinMock, State, Transaction as PinTransaction};
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use lis3mdl::registers::{INT_CFG, INT_SRC, OUT_X_L};
+use lis3mdl::{Error, SlaveAddr, LIS3MDL};
+
+const ADDRESS: u8 = 0x1E;
+
+#[test]
+fn wait_for_data_reads_once_drdy_goes_high() {
+    let i2c = I2cMock::new(&[I2cTransaction::write_read(
+        ADDRESS,
+        vec![OUT_X_L | 0x80],
+        vec![0x34, 0x12, 0xFE, 0xFF, 0x00, 0x7F],
+    )]);
+    let mut drdy = PinMock::new(&[
+        PinTransaction::get(State::Low),
+        PinTransaction::get(State::Low),
+        PinTransaction::get(State::High),
+    ]);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    let sample = lis3mdl
+        .wait_for_data(&mut drdy, &mut NoopDelay::new(), Duration::from_millis(1))
+        .unwrap();
+    assert_eq!(sample, (0x1234, -2, 0x7F00));
+
+    lis3mdl.destroy().done();
+    drdy.done();
+}
+
+#[test]
+fn wait_for_data_times_out() {
+    let i2c = I2cMock::new(&[]);
+    // Checked right away and then every 100 µs up to and including the timeout
+    let mut drdy = PinMock::new(&vec![PinTransaction::get(State::Low); 4]);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    assert_eq!(
+        lis3mdl.wait_for_data(&mut drdy, &mut NoopDelay::new(), Duration::from_micros(300)),
+        Err(Error::Timeout)
+    );
+
+    lis3mdl.destroy().done();
+    drdy.done();
+}
+
+#[test]
+fn wait_for_threshold_follows_int_polarity() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(ADDRESS, vec![INT_CFG], vec![0xE9]),
+        I2cTransaction::write_read(ADDRESS, vec![INT_SRC], vec![0b1000_0001]),
+    ]);
+    let mut int = PinMock::new(&[
+        PinTransaction::get(State::High),
+        PinTransaction::get(State::Low),
+    ]);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    let source = lis3mdl
+        .wait_for_threshold(&mut int, &mut NoopDelay::new(), Duration::from_millis(1))
+        .unwrap();
+    assert!(source.positive_x());
+    assert!(source.interrupt());
+
+    lis3mdl.destroy().done();
+    int.done();
+}
@@ -0,0 +1,70 @@
+use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use lis3mdl::registers::{CTRL_REG1, WHO_AM_I};
+use lis3mdl::LIS3MDL;
+
+/// CTRL_REG1 through CTRL_REG5 after power-on.
+const RESET_CONFIG: [u8; 5] = [0x10, 0x00, 0x03, 0x00, 0x00];
+
+#[test]
+fn new_falls_back_to_sa1_low_when_sa1_high_does_not_acknowledge() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x1E, vec![WHO_AM_I], vec![0])
+            .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+        I2cTransaction::write_read(0x1C, vec![WHO_AM_I], vec![0x3D]),
+        I2cTransaction::write_read(0x1C, vec![CTRL_REG1 | 0x80], RESET_CONFIG.to_vec()),
+    ]);
+
+    let lis3mdl = LIS3MDL::new(i2c).unwrap();
+
+    lis3mdl.destroy().done();
+}
+
+#[test]
+fn new_reports_foreign_device_when_sa1_low_does_not_acknowledge() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x1E, vec![WHO_AM_I], vec![0x48]),
+        I2cTransaction::write_read(0x1C, vec![WHO_AM_I], vec![0])
+            .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+    ]);
+
+    let mut bus = i2c.clone();
+    assert_eq!(
+        LIS3MDL::new(i2c).err(),
+        Some(lis3mdl::Error::WrongDeviceId(0x48))
+    );
+
+    bus.done();
+}
+
+#[test]
+fn new_reports_wrong_id_at_sa1_low() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x1E, vec![WHO_AM_I], vec![0])
+            .with_error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+        I2cTransaction::write_read(0x1C, vec![WHO_AM_I], vec![0x33]),
+    ]);
+
+    let mut bus = i2c.clone();
+    assert_eq!(
+        LIS3MDL::new(i2c).err(),
+        Some(lis3mdl::Error::WrongDeviceId(0x33))
+    );
+
+    bus.done();
+}
+
+#[test]
+fn new_reports_other_bus_errors() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(0x1E, vec![WHO_AM_I], vec![0]).with_error(ErrorKind::Bus)
+    ]);
+
+    let mut bus = i2c.clone();
+    assert_eq!(
+        LIS3MDL::new(i2c).err(),
+        Some(lis3mdl::Error::Bus(ErrorKind::Bus))
+    );
+
+    bus.done();
+}
@@ -0,0 +1,95 @@
+use core::time::Duration;
+
+use embedded_hal_mock::eh1::delay::NoopDelay;
+use embedded_hal_mock::eh1::digital::Mock as PinMock;
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use embedded_hal_mock::eh1::spi::{Mock as SpiMock, Transaction as SpiTransaction};
+use lis3mdl::registers::{CTRL_REG1, CTRL_REG5, OUT_X_H, OUT_X_L, STATUS_REG, WHO_AM_I};
+use lis3mdl::{Error, SlaveAddr, LIS3MDL};
+
+const ADDRESS: u8 = 0x1E;
+
+/// OUT_X_L through OUT_Z_H for x = 0x1234, y = -2 and z = 0x7F00.
+const OUTPUT: [u8; 6] = [0x34, 0x12, 0xFE, 0xFF, 0x00, 0x7F];
+
+#[test]
+fn i2c_read_auto_increments_and_decodes_axes() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write_read(ADDRESS, vec![STATUS_REG], vec![0b1000]),
+        I2cTransaction::write_read(ADDRESS, vec![OUT_X_L | 0x80], OUTPUT.to_vec()),
+    ]);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    assert_eq!(lis3mdl.read().unwrap(), (0x1234, -2, 0x7F00));
+
+    lis3mdl.destroy().done();
+}
+
+#[test]
+fn spi_read_auto_increments_and_decodes_axes() {
+    let spi = SpiMock::new(&[
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write(WHO_AM_I | 0x80),
+        SpiTransaction::read(0x3D),
+        SpiTransaction::transaction_end(),
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write(CTRL_REG1 | 0x80 | 0x40),
+        SpiTransaction::read_vec(vec![0x10, 0x00, 0x03, 0x00, 0x00]),
+        SpiTransaction::transaction_end(),
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write(STATUS_REG | 0x80),
+        SpiTransaction::read(0b1000),
+        SpiTransaction::transaction_end(),
+        SpiTransaction::transaction_start(),
+        SpiTransaction::write(OUT_X_L | 0x80 | 0x40),
+        SpiTransaction::read_vec(OUTPUT.to_vec()),
+        SpiTransaction::transaction_end(),
+    ]);
+
+    let mut lis3mdl = LIS3MDL::new_spi(spi).unwrap();
+    assert_eq!(lis3mdl.read().unwrap(), (0x1234, -2, 0x7F00));
+
+    lis3mdl.destroy().done();
+}
+
+#[test]
+fn read_fast_bursts_the_high_bytes_only() {
+    let i2c = I2cMock::new(&[
+        I2cTransaction::write(ADDRESS, vec![CTRL_REG5, 0x80]),
+        I2cTransaction::write_read(ADDRESS, vec![STATUS_REG], vec![0b1000]),
+        I2cTransaction::write_read(ADDRESS, vec![OUT_X_H | 0x80], vec![0x12, 0xFF, 0x80]),
+    ]);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    lis3mdl.set_fast_read(true).unwrap();
+    assert_eq!(lis3mdl.read_fast().unwrap(), (0x12, -1, -128));
+
+    lis3mdl.destroy().done();
+}
+
+#[test]
+fn read_fast_needs_fast_read_enabled() {
+    let i2c = I2cMock::new(&[]);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    assert_eq!(lis3mdl.read_fast(), Err(Error::InvalidConfiguration));
+
+    lis3mdl.destroy().done();
+}
+
+#[test]
+fn full_reads_are_refused_while_fast_read_is_enabled() {
+    let i2c = I2cMock::new(&[I2cTransaction::write(ADDRESS, vec![CTRL_REG5, 0x80])]);
+    let mut drdy = PinMock::new(&[]);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    lis3mdl.set_fast_read(true).unwrap();
+    assert_eq!(lis3mdl.read(), Err(Error::InvalidConfiguration));
+    assert_eq!(
+        lis3mdl.wait_for_data(&mut drdy, &mut NoopDelay::new(), Duration::from_millis(1)),
+        Err(Error::InvalidConfiguration)
+    );
+
+    lis3mdl.destroy().done();
+    drdy.done();
+}
@@ -0,0 +1,93 @@
+use embedded_hal_mock::eh1::delay::NoopDelay;
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use lis3mdl::registers::{CTRL_REG1, OUT_X_L, STATUS_REG};
+use lis3mdl::{SlaveAddr, LIS3MDL};
+
+const ADDRESS: u8 = 0x1E;
+
+/// CTRL_REG1 through CTRL_REG5 after power-on.
+const RESET_CONFIG: [u8; 5] = [0x10, 0x00, 0x03, 0x00, 0x00];
+/// ±12 gauss at 80 Hz, converting continuously.
+const SELF_TEST_CONFIG: [u8; 5] = [0x1C, 0x40, 0x00, 0x00, 0x00];
+
+fn apply(regs: [u8; 5]) -> Vec<I2cTransaction> {
+    vec![
+        I2cTransaction::transaction_start(ADDRESS),
+        I2cTransaction::write(ADDRESS, vec![CTRL_REG1 | 0x80]),
+        I2cTransaction::write(ADDRESS, regs.to_vec()),
+        I2cTransaction::transaction_end(ADDRESS),
+    ]
+}
+
+/// A discarded sample followed by five samples of `raw` at ±12 gauss.
+fn samples(raw: (i16, i16, i16)) -> Vec<I2cTransaction> {
+    let mut output = Vec::new();
+    for axis in [raw.0, raw.1, raw.2] {
+        output.extend_from_slice(&axis.to_le_bytes());
+    }
+
+    let mut transactions = Vec::new();
+    for i in 0..6 {
+        let values = if i == 0 {
+            vec![0x7F; 6]
+        } else {
+            output.clone()
+        };
+        transactions.push(I2cTransaction::write_read(
+            ADDRESS,
+            vec![STATUS_REG],
+            vec![0b1000],
+        ));
+        transactions.push(I2cTransaction::write_read(
+            ADDRESS,
+            vec![OUT_X_L | 0x80],
+            values,
+        ));
+    }
+    transactions
+}
+
+fn self_test_transactions(off: (i16, i16, i16), on: (i16, i16, i16)) -> Vec<I2cTransaction> {
+    let mut transactions = apply(SELF_TEST_CONFIG);
+    transactions.extend(samples(off));
+    transactions.push(I2cTransaction::write(ADDRESS, vec![CTRL_REG1, 0x1D]));
+    transactions.extend(samples(on));
+    transactions.extend(apply(RESET_CONFIG));
+    transactions
+}
+
+#[test]
+fn self_test_passes_within_limits_and_restores_config() {
+    // Deltas of 2, -1.5 and 0.5 gauss at 2281 LSB/gauss
+    let i2c = I2cMock::new(&self_test_transactions(
+        (100, 200, -300),
+        (4662, -3222, 840),
+    ));
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    let before = lis3mdl.config();
+    let report = lis3mdl.self_test(&mut NoopDelay::new()).unwrap();
+    assert!(report.passed);
+    assert!((report.delta.x - 2.0).abs() < 0.001);
+    assert!((report.delta.y + 1.5).abs() < 0.001);
+    assert!((report.delta.z - 0.5).abs() < 0.001);
+    assert_eq!(lis3mdl.config(), before);
+
+    lis3mdl.destroy().done();
+}
+
+#[test]
+fn self_test_fails_outside_limits() {
+    // The z delta of 1.5 gauss is above its 1 gauss limit
+    let i2c = I2cMock::new(&self_test_transactions((0, 0, 0), (4562, 4562, 3422)));
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    let report = lis3mdl.self_test(&mut NoopDelay::new()).unwrap();
+    assert!(!report.passed);
+    assert_eq!(
+        report.into_result::<()>(),
+        Err(lis3mdl::Error::SelfTestFailed)
+    );
+
+    lis3mdl.destroy().done();
+}
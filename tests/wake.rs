@@ -0,0 +1,77 @@
+use embedded_hal_mock::eh1::i2c::{Mock as I2cMock, Transaction as I2cTransaction};
+use lis3mdl::registers::{CTRL_REG1, INT_CFG, INT_SRC, INT_THS_L};
+use lis3mdl::{Axes, SlaveAddr, LIS3MDL};
+
+const ADDRESS: u8 = 0x1E;
+
+/// CTRL_REG1 through CTRL_REG5 after power-on.
+const RESET_CONFIG: [u8; 5] = [0x10, 0x00, 0x03, 0x00, 0x00];
+/// Low-power axis modes, 0.625 Hz, LP set and converting continuously.
+const WAKE_CONFIG: [u8; 5] = [0x00, 0x00, 0x20, 0x00, 0x00];
+
+fn burst_write(reg: u8, values: &[u8]) -> Vec<I2cTransaction> {
+    vec![
+        I2cTransaction::transaction_start(ADDRESS),
+        I2cTransaction::write(ADDRESS, vec![reg | 0x80]),
+        I2cTransaction::write(ADDRESS, values.to_vec()),
+        I2cTransaction::transaction_end(ADDRESS),
+    ]
+}
+
+/// Arming once the previous state has been saved: the low-power configuration first,
+/// then the threshold and INT_CFG, then clearing INT_SRC.
+fn arm(int_ths: [u8; 2], int_cfg: u8) -> Vec<I2cTransaction> {
+    let mut transactions = burst_write(CTRL_REG1, &WAKE_CONFIG);
+    transactions.extend(burst_write(INT_THS_L, &int_ths));
+    transactions.push(I2cTransaction::write(ADDRESS, vec![INT_CFG, int_cfg]));
+    transactions.push(I2cTransaction::write_read(
+        ADDRESS,
+        vec![INT_SRC],
+        vec![0x00],
+    ));
+    transactions
+}
+
+#[test]
+fn arm_saves_state_and_disarm_restores_it() {
+    let mut transactions = vec![
+        // Saving INT_CFG, active high and pulsed, and INT_THS
+        I2cTransaction::write_read(ADDRESS, vec![INT_CFG], vec![0xEE]),
+        I2cTransaction::write_read(ADDRESS, vec![INT_THS_L | 0x80], vec![0x34, 0x12]),
+    ];
+    // 1 gauss at ±4 gauss is 6842 LSB, on all axes, active high and latched
+    transactions.extend(arm([0xBA, 0x1A], 0b1110_1101));
+    // Arming again does not save the armed state over the original one
+    transactions.extend(arm([0x5D, 0x0D], 0b0010_1101));
+    transactions.extend(burst_write(CTRL_REG1, &RESET_CONFIG));
+    transactions.push(I2cTransaction::write(ADDRESS, vec![INT_CFG, 0xEE]));
+    transactions.extend(burst_write(INT_THS_L, &[0x34, 0x12]));
+    transactions.push(I2cTransaction::write_read(
+        ADDRESS,
+        vec![INT_SRC],
+        vec![0x00],
+    ));
+    let i2c = I2cMock::new(&transactions);
+
+    let mut lis3mdl = LIS3MDL::new_with_address(i2c, SlaveAddr::Sa1High);
+    let before = lis3mdl.config();
+    lis3mdl.arm_wake_on_field(1.0, Axes::ALL).unwrap();
+    assert!(lis3mdl.config().low_power);
+    lis3mdl
+        .arm_wake_on_field(
+            0.5,
+            Axes {
+                x: false,
+                y: false,
+                z: true,
+            },
+        )
+        .unwrap();
+    lis3mdl.disarm().unwrap();
+    assert_eq!(lis3mdl.config(), before);
+
+    // Disarming again does nothing
+    lis3mdl.disarm().unwrap();
+
+    lis3mdl.destroy().done();
+}